@@ -0,0 +1,131 @@
+//! cargo features of dyer selected by `dyer new --features <a,b>` or `dyer new -i`
+//!
+//! the selected features are written into the dependency of dyer in `Cargo.toml`,
+//! along with the code that makes use of them appended to the generated files.
+use std::io::{BufRead, Write};
+
+/// name and description of the features of dyer, by the supported version
+pub(crate) fn get_features(version: &str) -> &'static [(&'static str, &'static str)] {
+    match version {
+        "3.2" => &[(
+            "compression",
+            "decompress responses encoded with br, deflate or gzip",
+        )],
+        _ => &[
+            (
+                "compression",
+                "decompress responses encoded with br, deflate or gzip",
+            ),
+            (
+                "proxy",
+                "send tasks through proxies, with basic, bearer or custom authentication",
+            ),
+            (
+                "xpath",
+                "parse html of responses with xpath, requires libxml2 installed",
+            ),
+            ("full", "all of the features above"),
+        ],
+    }
+}
+
+/// check the features given as `a,b` or `a b` against the version, in the order of [`get_features`]
+pub(crate) fn parse_features(version: &str, features: &str) -> Result<Vec<&'static str>, String> {
+    let available = get_features(version);
+    let mut selected = Vec::new();
+    for feature in features.split([',', ' ']).map(|f| f.trim()) {
+        if feature.is_empty() {
+            continue;
+        }
+        // the index shown by the interactive prompt is accepted as well
+        let found = match feature.parse::<usize>() {
+            Ok(index) if index >= 1 => available.get(index - 1),
+            _ => available.iter().find(|(name, _)| *name == feature),
+        };
+        match found {
+            Some((name, _)) if !selected.contains(name) => selected.push(*name),
+            Some(_) => {}
+            None => {
+                let names = available.iter().map(|(n, _)| *n).collect::<Vec<&str>>();
+                return Err(format!(
+                    "unknown feature `{}` of dyer {}, available features: {}",
+                    feature,
+                    version,
+                    names.join(", ")
+                ));
+            }
+        }
+    }
+    selected.sort_by_key(|f| available.iter().position(|(name, _)| name == f));
+    Ok(selected)
+}
+
+/// list the features of the version and read the selected ones from stdin
+pub(crate) fn prompt_features(version: &str) -> Result<Vec<&'static str>, String> {
+    println!("features of dyer {}:", version);
+    for (i, (name, description)) in get_features(version).iter().enumerate() {
+        println!("    {}) {:<16}{}", i + 1, name, description);
+    }
+    print!("features to enable, names or numbers separated by comma(none if empty): ");
+    std::io::stdout().flush().unwrap();
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("failed to read the features: {}", e))?;
+    parse_features(version, &line)
+}
+
+/// the code appended to file `index` when the feature is enabled
+pub(crate) fn get_feature_intro(feature: &str, index: &str) -> Option<&'static str> {
+    let intro = match (feature, index) {
+        ("proxy", "middleware") => {
+            r#"
+/// the proxy tasks go through, build the task with `Task::builder().proxy(PROXY)` to use it
+pub const PROXY: &str = "http://127.0.0.1:1080";
+
+/* requires feature `proxy` of dyer, rotate or authenticate the proxy of tasks before requested,
+ * eg. `proxy.set_auth_basic(username, password)`
+ */
+#[dyer::middleware(handle_task)]
+pub async fn handle_proxy(tasks: &mut Vec<Task>, _app: &mut App<Entities>) {
+    for task in tasks.iter_mut() {
+        if let Some(proxy) = task.proxy_mut() {
+            proxy.set_addr(PROXY);
+        }
+    }
+}
+"#
+        }
+        ("xpath", "parser") => {
+            r#"
+/* requires feature `xpath` of dyer, the nodes of html matched by xpath,
+ * use it as `.parser(parse_xpath)` of task
+ */
+#[dyer::parser]
+pub fn parse_xpath(mut res: Response) -> Parsed<Entities> {
+    let _titles = res
+        .xpath("//title/text()")
+        .iter()
+        .map(|node| node.get_content())
+        .collect::<Vec<String>>();
+    Parsed::new()
+}
+"#
+        }
+        _ => return None,
+    };
+    Some(intro)
+}
+
+/// the features whose code is generated, `full` stands for all of them
+pub(crate) fn expand_features(version: &str, features: &[&'static str]) -> Vec<&'static str> {
+    if features.contains(&"full") {
+        return get_features(version)
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| *name != "full")
+            .collect();
+    }
+    features.to_vec()
+}
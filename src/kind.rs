@@ -0,0 +1,672 @@
+//! built-in starter kinds used by `dyer new --kind <kind>`
+//!
+//! each kind replaces the empty scaffold with a crawler of common shape that
+//! actually follows links and emits entities, along with a fixture under
+//! `tests/fixtures/<parser>/` and an integration test feeding it to the parser.
+
+/// name and description of the supported kinds
+pub(crate) const KINDS: [(&str, &str); 4] = [
+    (
+        "sitemap",
+        "follow sitemaps and sitemap indexes, collect the title of every page",
+    ),
+    (
+        "paginated-list",
+        "collect the items of a listing page and follow its `next` link",
+    ),
+    (
+        "json-api",
+        "deserialize a paginated JSON API and follow the `next` cursor",
+    ),
+    (
+        "login-session",
+        "log in with a form, keep the session cookie and crawl protected pages",
+    ),
+];
+
+/// dependencies used by the generated parsers and pipelines
+pub(crate) const KIND_DEPENDENCIES: &str = r#"regex = "1.5"
+serde_json = "1.0""#;
+
+/// placeholders of the actor template `<+start_uri+>` and `<+entry_parser+>`
+pub(crate) fn get_kind_vars(kind: &str) -> [(&'static str, &'static str); 2] {
+    match kind {
+        "sitemap" => [
+            ("start_uri", "https://example.com/sitemap.xml"),
+            ("entry_parser", "parse_sitemap"),
+        ],
+        "paginated-list" => [
+            ("start_uri", "https://example.com/items?page=1"),
+            ("entry_parser", "parse_list"),
+        ],
+        "json-api" => [
+            ("start_uri", "https://example.com/api/records?page=1"),
+            ("entry_parser", "parse_api"),
+        ],
+        "login-session" => [
+            ("start_uri", "https://example.com/login"),
+            ("entry_parser", "parse_login"),
+        ],
+        _ => [
+            ("start_uri", "https://example.com/some/path/to/site"),
+            ("entry_parser", "parse_func"),
+        ],
+    }
+}
+
+/// the content of file `index` for the kind, `None` if the default one is used
+pub(crate) fn get_kind_intro(kind: &str, index: &str) -> Option<&'static str> {
+    let intro = match (kind, index) {
+        ("sitemap", "entity") => {
+            r#"use serde::{Deserialize, Serialize};
+
+// a page listed in the sitemap
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Page {
+    pub url: String,
+    pub title: String,
+}
+
+/* serve as a container for all entities, and generic parameter of dyer::App
+ * attribute #[dyer::entity(entities)] mark the enum and use it as container to all data to be collected
+ */
+#[dyer::entity(entities)]
+#[derive(Serialize, Debug, Clone)]
+pub enum Entities {
+    Page(Page),
+}
+"#
+        }
+        ("sitemap", "parser") => {
+            r#"use crate::entity::*;
+use dyer::*;
+
+/* a sitemap lists pages in `<loc>`, a sitemap index lists other sitemaps the same way,
+ * so locations ending with `.xml` are parsed by `parse_sitemap` again, others by `parse_page`
+ */
+#[dyer::parser]
+pub fn parse_sitemap(res: Response) -> Parsed<Entities> {
+    let mut parsed = Parsed::new();
+    let body = String::from_utf8_lossy(&<+res_bytes+>).to_string();
+    let loc = regex::Regex::new(r"(?s)<loc>\s*(.*?)\s*</loc>").unwrap();
+    for cap in loc.captures_iter(&body) {
+        let uri = cap[1].replace("&amp;", "&");
+        let builder = if uri.ends_with(".xml") {
+            Task::get(uri.as_str()).parser(parse_sitemap)
+        } else {
+            Task::get(uri.as_str()).parser(parse_page)
+        };
+        if let Ok(task) = builder.body(Body::empty(), <+actor_marker+>) {
+            parsed.task.push(task);
+        }
+    }
+    parsed
+}
+
+// collect the title of page listed in the sitemap
+#[dyer::parser]
+pub fn parse_page(res: Response) -> Parsed<Entities> {
+    let mut parsed = Parsed::new();
+    let body = String::from_utf8_lossy(&<+res_bytes+>).to_string();
+    let title = regex::Regex::new(r"(?is)<title[^>]*>\s*(.*?)\s*</title>").unwrap();
+    if let Some(cap) = title.captures(&body) {
+        parsed.entities.push(Entities::Page(Page {
+            url: res.metas.info.from.to_string(),
+            title: cap[1].to_string(),
+        }));
+    }
+    parsed
+}
+"#
+        }
+        ("paginated-list", "entity") => {
+            r#"use serde::{Deserialize, Serialize};
+
+// an item of the listing page
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Item {
+    pub title: String,
+    pub url: String,
+}
+
+/* serve as a container for all entities, and generic parameter of dyer::App
+ * attribute #[dyer::entity(entities)] mark the enum and use it as container to all data to be collected
+ */
+#[dyer::entity(entities)]
+#[derive(Serialize, Debug, Clone)]
+pub enum Entities {
+    Item(Item),
+}
+"#
+        }
+        ("paginated-list", "parser") => {
+            r##"use crate::entity::*;
+use dyer::*;
+
+/* collect every `<a class="item">` of the listing page,
+ * and follow the `<a rel="next">` link with `parse_list` again until the last page
+ * adjust the patterns to the site you crawl
+ */
+#[dyer::parser]
+pub fn parse_list(res: Response) -> Parsed<Entities> {
+    let mut parsed = Parsed::new();
+    let base = res.metas.info.from.to_string();
+    let body = String::from_utf8_lossy(&<+res_bytes+>).to_string();
+    let item = regex::Regex::new(r#"(?s)<a[^>]*class="item"[^>]*href="([^"]*)"[^>]*>\s*(.*?)\s*</a>"#).unwrap();
+    for cap in item.captures_iter(&body) {
+        parsed.entities.push(Entities::Item(Item {
+            title: cap[2].to_string(),
+            url: absolute(&base, &cap[1]),
+        }));
+    }
+    let next = regex::Regex::new(r#"<a[^>]*rel="next"[^>]*href="([^"]*)""#).unwrap();
+    if let Some(cap) = next.captures(&body) {
+        let uri = absolute(&base, &cap[1].replace("&amp;", "&"));
+        if let Ok(task) = Task::get(uri.as_str())
+            .parser(parse_list)
+            .body(Body::empty(), <+actor_marker+>)
+        {
+            parsed.task.push(task);
+        }
+    }
+    parsed
+}
+
+// resolve `href` found in the page whose uri is `base`
+pub fn absolute(base: &str, href: &str) -> String {
+    if href.starts_with("http://") || href.starts_with("https://") {
+        return href.to_string();
+    }
+    let origin_end = base
+        .find("://")
+        .map(|i| i + 3 + base[i + 3..].find('/').unwrap_or(base.len() - i - 3))
+        .unwrap_or(0);
+    if href.starts_with('/') {
+        return format!("{}{}", &base[..origin_end], href);
+    }
+    let path = base.split(|c| c == '?' || c == '#').next().unwrap_or(base);
+    match path.rfind('/') {
+        Some(i) if i >= origin_end => format!("{}{}", &path[..i + 1], href),
+        _ => format!("{}/{}", path, href),
+    }
+}
+"##
+        }
+        ("json-api", "entity") => {
+            r#"use serde::{Deserialize, Serialize};
+
+// a record returned by the API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Record {
+    pub id: u64,
+    pub name: String,
+}
+
+// a page of the API, `next` is the uri of the next page
+#[derive(Deserialize, Debug)]
+pub struct ApiPage {
+    pub data: Vec<Record>,
+    pub next: Option<String>,
+}
+
+/* serve as a container for all entities, and generic parameter of dyer::App
+ * attribute #[dyer::entity(entities)] mark the enum and use it as container to all data to be collected
+ */
+#[dyer::entity(entities)]
+#[derive(Serialize, Debug, Clone)]
+pub enum Entities {
+    Record(Record),
+}
+"#
+        }
+        ("json-api", "parser") => {
+            r#"use crate::entity::*;
+use dyer::*;
+
+/* deserialize the page of API into `ApiPage`, collect its records,
+ * and follow the `next` cursor with `parse_api` again until it is null
+ */
+#[dyer::parser]
+pub fn parse_api(res: Response) -> Parsed<Entities> {
+    let mut parsed = Parsed::new();
+    let page: ApiPage = match serde_json::from_slice(&<+res_bytes+>) {
+        Ok(page) => page,
+        Err(e) => {
+            log::error!("failed to deserialize {}: {}", res.metas.info.from, e);
+            return parsed;
+        }
+    };
+    parsed
+        .entities
+        .extend(page.data.into_iter().map(Entities::Record));
+    if let Some(next) = page.next {
+        if let Ok(task) = Task::get(next.as_str())
+            .header("accept", "application/json")
+            .parser(parse_api)
+            .body(Body::empty(), <+actor_marker+>)
+        {
+            parsed.task.push(task);
+        }
+    }
+    parsed
+}
+"#
+        }
+        ("login-session", "entity") => {
+            r#"use serde::{Deserialize, Serialize};
+
+// the profile shown on the page behind the login
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub email: String,
+}
+
+/* serve as a container for all entities, and generic parameter of dyer::App
+ * attribute #[dyer::entity(entities)] mark the enum and use it as container to all data to be collected
+ */
+#[dyer::entity(entities)]
+#[derive(Serialize, Debug, Clone)]
+pub enum Entities {
+    Profile(Profile),
+}
+"#
+        }
+        ("login-session", "parser") => {
+            r##"use crate::entity::*;
+use dyer::*;
+
+/* the response of login form carries the session in `set-cookie`,
+ * keep it in the `cookie` header of the tasks to pages behind the login
+ */
+#[dyer::parser]
+pub fn parse_login(res: Response) -> Parsed<Entities> {
+    let mut parsed = Parsed::new();
+    let cookie = res
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|val| val.to_str().ok())
+        .filter_map(|val| val.split(';').next())
+        .collect::<Vec<&str>>()
+        .join("; ");
+    if cookie.is_empty() {
+        log::error!("login failed, no session cookie returned by {}", res.metas.info.from);
+        return parsed;
+    }
+    if let Ok(task) = Task::get("https://example.com/account")
+        .header("cookie", cookie.as_str())
+        .parser(parse_account)
+        .body(Body::empty(), <+actor_marker+>)
+    {
+        parsed.task.push(task);
+    }
+    parsed
+}
+
+// collect the profile on the page behind the login
+#[dyer::parser]
+pub fn parse_account(res: Response) -> Parsed<Entities> {
+    let mut parsed = Parsed::new();
+    let body = String::from_utf8_lossy(&<+res_bytes+>).to_string();
+    let field = |class: &str| {
+        let pat = format!(r#"(?s)class="{}"[^>]*>\s*(.*?)\s*<"#, class);
+        regex::Regex::new(&pat)
+            .unwrap()
+            .captures(&body)
+            .map(|cap| cap[1].to_string())
+    };
+    if let (Some(name), Some(email)) = (field("name"), field("email")) {
+        parsed.entities.push(Entities::Profile(Profile { name, email }));
+    }
+    parsed
+}
+"##
+        }
+        ("login-session", "actor") => {
+            r#"pub mod affix;
+pub mod entity;
+pub mod middleware;
+pub mod pipeline;
+pub mod parser;
+
+use affix::*;
+use entity::*;
+use parser::*;
+use dyer::*;
+
+// attribute #[dyer::actor] mark the struct and use it as a type implemented trait `Actor`
+#[dyer::actor]
+pub struct MyActor {
+    pub login_uri: String,
+}
+
+#[dyer::async_trait]
+impl Actor<Entities, Aff> for MyActor {
+    // create an instance
+    async fn new() -> Self {
+        MyActor{
+            login_uri: "<+start_uri+>".into()
+        }
+    }
+
+    // preparation before opening actor
+    async fn open_actor(&mut self, _app: &mut App<Entities>) {}
+
+    /*
+     * post the login form with credentials from environment variables `LOGIN_USER` and `LOGIN_PASSWORD`,
+     * `parse_login` picks the session cookie and continues to pages behind the login
+     */
+    async fn entry_task(&mut self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+        let user = std::env::var("LOGIN_USER")?;
+        let password = std::env::var("LOGIN_PASSWORD")?;
+        let form = format!("username={}&password={}", user, password);
+        let task = Task::post( &self.login_uri )
+            .header("content-type", "application/x-www-form-urlencoded")
+            .parser(<+entry_parser+>)
+            // here the marker `MyActor` is the same as
+            // the type implemented trait `Acotr`
+            // change it if you like as long as it is unique
+            .body(Body::from(form), <+actor_marker+>)?;
+        Ok(vec![task])
+    }
+
+    /* the generator of `Affix`
+     * `dyer` consume the returned `Request`, generate a `Response` fed to the closure
+     * to generate a `Affix`
+     */
+    async fn entry_affix(&mut self) -> Option<Aff> {
+        None
+    }
+
+    // preparation before closing actor
+    async fn close_actor(&mut self, _app: &mut App<Entities>) {}
+}"#
+        }
+        (_, "pipeline") if KINDS.iter().any(|(k, _)| k == &kind) => {
+            r#"use dyer::*;
+use crate::entity::*;
+use std::io::Write;
+
+ /*
+ * something to do before sending entities to pipeline
+ * the return type inside `Option` requires complete path(starts with `std` or crate in `Cargo.toml`)
+ * attribute #[dyer::pipeline(attr)] mark the method and use it as that in `PipeLine`
+ * attr could be:
+ *    initializer/disposer/process_entity/process_yerr
+ */
+#[dyer::pipeline(initializer)]
+async fn func_name(_app: &mut App<Entities>) -> Option<std::fs::File>
+{
+    None
+}
+
+// append the collected entities as json lines to `data/entities.jsonl`
+#[dyer::pipeline(process_entity)]
+async fn store_entities(items: Vec<Entities>, _app: &mut App<Entities>) {
+    let mut file = match std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open("data/entities.jsonl")
+    {
+        Ok(file) => file,
+        Err(e) => {
+            log::error!("failed to open data/entities.jsonl: {}", e);
+            return;
+        }
+    };
+    for item in items.iter() {
+        if let Ok(line) = serde_json::to_string(item) {
+            writeln!(file, "{}", line).ok();
+        }
+    }
+}
+"#
+        }
+        _ => return None,
+    };
+    Some(intro)
+}
+
+/// fixtures and tests of the kind, paths are relative to the project
+pub(crate) fn get_kind_files(kind: &str) -> Vec<(&'static str, &'static str)> {
+    match kind {
+        "sitemap" => vec![
+            (
+                "tests/fixtures/parse_sitemap/sitemap.xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>https://example.com/about</loc>
+    <lastmod>2021-06-01</lastmod>
+  </url>
+  <url>
+    <loc>https://example.com/blog?tag=rust&amp;page=2</loc>
+  </url>
+  <sitemap>
+    <loc>https://example.com/sitemap-blog.xml</loc>
+  </sitemap>
+</urlset>
+"#,
+            ),
+            (
+                "tests/fixtures/parse_page/about.html",
+                r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>About Us</title>
+  </head>
+  <body><h1>About Us</h1></body>
+</html>
+"#,
+            ),
+            (
+                "tests/sitemap.rs",
+                r#"use <+crate_name+>::entity::*;
+use <+crate_name+>::parser::*;
+use dyer::*;
+
+fn response(uri: &str, body: &'static str) -> Response {
+    let mut res = Response::new(body);
+    res.metas.info.from = uri.parse().unwrap();
+    res
+}
+
+#[test]
+fn sitemap_follows_locations() {
+    let res = response(
+        "https://example.com/sitemap.xml",
+        include_str!("fixtures/parse_sitemap/sitemap.xml"),
+    );
+    let parsed = parse_sitemap(res);
+    let uris = parsed
+        .task
+        .iter()
+        .map(|task| task.uri().to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        uris,
+        vec![
+            "https://example.com/about",
+            "https://example.com/blog?tag=rust&page=2",
+            "https://example.com/sitemap-blog.xml",
+        ]
+    );
+}
+
+#[test]
+fn page_emits_title() {
+    let res = response(
+        "https://example.com/about",
+        include_str!("fixtures/parse_page/about.html"),
+    );
+    let parsed = parse_page(res);
+    assert_eq!(parsed.entities.len(), 1);
+    match &parsed.entities[0] {
+        Entities::Page(page) => {
+            assert_eq!(page.title, "About Us");
+            assert_eq!(page.url, "https://example.com/about");
+        }
+    }
+}
+"#,
+            ),
+        ],
+        "paginated-list" => vec![
+            (
+                "tests/fixtures/parse_list/page1.html",
+                r#"<!DOCTYPE html>
+<html>
+  <body>
+    <ul>
+      <li><a class="item" href="/items/1">First item</a></li>
+      <li><a class="item" href="https://example.com/items/2">Second item</a></li>
+      <li><a class="item" href="items/3">Third item</a></li>
+    </ul>
+    <a rel="next" href="/items?page=2">Next</a>
+  </body>
+</html>
+"#,
+            ),
+            (
+                "tests/paginated_list.rs",
+                r#"use <+crate_name+>::entity::*;
+use <+crate_name+>::parser::*;
+use dyer::*;
+
+#[test]
+fn list_emits_items_and_follows_next() {
+    let mut res = Response::new(include_str!("fixtures/parse_list/page1.html"));
+    res.metas.info.from = "https://example.com/items?page=1".parse().unwrap();
+    let parsed = parse_list(res);
+    let urls = parsed
+        .entities
+        .iter()
+        .map(|entity| match entity {
+            Entities::Item(item) => item.url.as_str(),
+        })
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        urls,
+        vec![
+            "https://example.com/items/1",
+            "https://example.com/items/2",
+            "https://example.com/items/3",
+        ]
+    );
+    assert_eq!(parsed.task.len(), 1);
+    assert_eq!(
+        parsed.task[0].uri().to_string(),
+        "https://example.com/items?page=2"
+    );
+}
+"#,
+            ),
+        ],
+        "json-api" => vec![
+            (
+                "tests/fixtures/parse_api/page1.json",
+                r#"{
+  "data": [
+    { "id": 1, "name": "first" },
+    { "id": 2, "name": "second" }
+  ],
+  "next": "https://example.com/api/records?page=2"
+}
+"#,
+            ),
+            (
+                "tests/fixtures/parse_api/last.json",
+                r#"{
+  "data": [
+    { "id": 3, "name": "third" }
+  ],
+  "next": null
+}
+"#,
+            ),
+            (
+                "tests/json_api.rs",
+                r#"use <+crate_name+>::entity::*;
+use <+crate_name+>::parser::*;
+use dyer::*;
+
+#[test]
+fn api_emits_records_and_follows_next() {
+    let parsed = parse_api(Response::new(include_str!("fixtures/parse_api/page1.json")));
+    assert_eq!(parsed.entities.len(), 2);
+    match &parsed.entities[1] {
+        Entities::Record(record) => assert_eq!(record.name, "second"),
+    }
+    assert_eq!(parsed.task.len(), 1);
+    assert_eq!(
+        parsed.task[0].uri().to_string(),
+        "https://example.com/api/records?page=2"
+    );
+}
+
+#[test]
+fn api_stops_at_last_page() {
+    let parsed = parse_api(Response::new(include_str!("fixtures/parse_api/last.json")));
+    assert_eq!(parsed.entities.len(), 1);
+    assert!(parsed.task.is_empty());
+}
+"#,
+            ),
+        ],
+        "login-session" => vec![
+            (
+                "tests/fixtures/parse_account/account.html",
+                r#"<!DOCTYPE html>
+<html>
+  <body>
+    <div class="profile">
+      <span class="name">Jane Doe</span>
+      <span class="email">jane@example.com</span>
+    </div>
+  </body>
+</html>
+"#,
+            ),
+            (
+                "tests/login_session.rs",
+                r#"use <+crate_name+>::entity::*;
+use <+crate_name+>::parser::*;
+use dyer::*;
+
+#[test]
+fn login_keeps_session_cookie() {
+    let res = Response::builder()
+        .header("set-cookie", "session=abc123; Path=/; HttpOnly")
+        .body("");
+    let parsed = parse_login(res);
+    assert_eq!(parsed.task.len(), 1);
+    let cookie = parsed.task[0].headers().get("cookie").unwrap();
+    assert_eq!(cookie.to_str().unwrap(), "session=abc123");
+}
+
+#[test]
+fn login_without_cookie_stops() {
+    let parsed = parse_login(Response::new(""));
+    assert!(parsed.task.is_empty());
+}
+
+#[test]
+fn account_emits_profile() {
+    let parsed = parse_account(Response::new(include_str!(
+        "fixtures/parse_account/account.html"
+    )));
+    assert_eq!(parsed.entities.len(), 1);
+    match &parsed.entities[0] {
+        Entities::Profile(profile) => {
+            assert_eq!(profile.name, "Jane Doe");
+            assert_eq!(profile.email, "jane@example.com");
+        }
+    }
+}
+"#,
+            ),
+        ],
+        _ => Vec::new(),
+    }
+}
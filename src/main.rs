@@ -36,6 +36,19 @@
 //!     |___src/middleware.rs
 //!     |___src/pipeline.rs
 //! ```
+//! `dyer new myproject --template <path|name>` renders a template directory instead, either a path or the name of
+//! a template installed in `~/.config/dyer/templates`, placeholders `<+name+>`, `<+log_level+>`, `<+author+>`,
+//! `<+crate_name+>` and `<+dyer_version+>` are replaced in file contents and file names, `--var key=value` defines more of them,
+//! and `template.cfg` at the root of template gives its description and files rendered only when some
+//! variable is set, `dyer new --list-templates` shows the installed templates.
+//!
+//! `dyer new myproject --kind <kind>` starts from a working crawler instead of an empty one, with fixtures under
+//! `tests/fixtures/<parser>/` and a test feeding them to the parsers, the kinds are:
+//! * `sitemap` follows sitemaps and sitemap indexes, collects the title of every page
+//! * `paginated-list` collects the items of a listing page and follows its `next` link
+//! * `json-api` deserializes a paginated JSON API and follows the `next` cursor
+//! * `login-session` logs in with a form, keeps the session cookie and crawls protected pages
+//!
 //! Main functionality of each file:                                        
 //! * the `affix.rs` serves as an actor to adjust and satisfy additional requirement
 //! * the `entity.rs` contains entities/data structure to be used/collected
@@ -47,6 +60,50 @@
 //! * `README.md` contains some instructions of the project
 //! * `data` folder balance the app load when data in app exceeds, and backup app data at certain gap
 //!
+//! `dyer new` refuses to write into a directory that is not empty, `--force` overwrites the files in it.
+//!
+//! The package is named after the last component of the directory, `dyer new path/to/dir --name foo` names it
+//! `foo` instead. The name consists of lowercase letters, digits, `-` and `_`, and must not be a Rust keyword
+//! nor a crate the project uses such as `dyer` or `log`, the library and the generated main use the crate
+//! identifier derived from it, `-` replaced with `_`.
+//!
+//! Like cargo, `dyer new` initializes a git repository when git is installed and the project is not already
+//! inside one, with a `.gitignore` excluding `target/`, `data/` and `.dyer-fingerprint`, the generated main is
+//! kept under version control for its user regions unless its line there is uncommented, `--vcs git` always
+//! initializes it and `--vcs none` skips it.
+//!
+//! The project depends on dyer 3.3, `dyer new myproject --dyer-version 3.2` depends on dyer 3.2 instead, a release
+//! such as `3.2.1` pins it. The version in `Cargo.toml` is what later subcommands read to generate the code
+//! compatible with it, supported versions are `3.3` and `3.2`.
+//!
+//! `dyer new myproject --features proxy,xpath` enables cargo features of dyer, `dyer new myproject -i` lists
+//! them with their descriptions and asks which to enable. Features that need code get it generated too, a
+//! `handle_proxy` middleware for `proxy` and a `parse_xpath` parser for `xpath`. The features of dyer 3.3 are
+//! `compression`, `proxy`, `xpath` and `full`, dyer 3.2 only has `compression`. They cannot be used with
+//! `--template`, whose Cargo.toml names the features itself.
+//!
+//! Most crawlers need no affix, `dyer new myproject --no-affix` leaves out `src/affix.rs` and the actor uses
+//! an inert `NoAffix` instead, templates get the variable `no_affix` set. A project without `src/affix.rs`, or
+//! whose `entry_affix` returns `None`, gets a main without the `handle_affix` middleware.
+//!
+//! ## dyer init
+//!
+//! Turn the current directory(or `dyer init path/to/dir`), an existing cargo crate or not, into a dyer project.
+//! Files already there are kept and only the missing ones are added, the dependencies of dyer and the `[lib]`,
+//! `[[bin]]` sections are merged into the existing `Cargo.toml`. If the crate has its own library root such as
+//! `src/lib.rs`, the modules of dyer are declared there instead.
+//!
+//! The following subcommands(`check`, `fix`, `run`, `build`, `test` and `clean`) all generate the main in `src/bin/` from the annotated items of the library before
+//! running cargo. `.dyer-fingerprint` records what it is made from, the hash of `Cargo.toml`, `dyer.cfg` and every
+//! source of the library, the version of dyer-cli and the settings of generation, and is written only after the
+//! main is generated, the main is regenerated whenever any of them changes.
+//!
+//! The hooks named by `#[dyer::middleware(..)]` and `#[dyer::pipeline(..)]` are checked before the main is
+//! generated, an unknown one is reported with the closest valid hook, eg. "did you mean `handle_entity`?". The
+//! functions bound to them are checked against the signatures dyer expects as well, so that a mistake is reported
+//! with its file and line, such as "`handle_entity` expects `&mut Vec<Entities>, &mut App<Entities>`", instead of
+//! type errors in the generated main.
+//!
 //! ## dyer check
 //!
 //! A warper of `cargo check`, if you run it the first time,`dyer-cli` will download the crates and then check the code.
@@ -57,7 +114,24 @@
 //!
 //! ## dyer run
 //!
-//! A wraper of `cargo run`, when the program compiles, run it.
+//! A wraper of `cargo run`, when the program compiles, run it. SIGTERM sent to `dyer run` is forwarded to the
+//! program, which shuts down before `dyer run` exits, as Ctrl+c does.
+//!
+//! With the `signal` feature of tokio, which new projects enable, the generated main shuts the app down
+//! gracefully on Ctrl+c or SIGTERM: entities are flushed through the pipeline and pending tasks are stored in
+//! `data/` for the next run. Another signal, or the grace period passed, exits at once, `grace-period` of
+//! `[package.metadata.dyer]` in `Cargo.toml` sets it in seconds, 30 as default.
+//!
+//! `dyer run --record <dir>` generates a main that saves every response into a cassette in `<dir>`, the bodies
+//! as `<n>.body`, the status and headers as `<n>.head`, the bodies of requests as `<n>.req` and an `index` of
+//! their method, request body digest and uri, so cookies and other headers replay as well. `dyer run --replay
+//! <dir>` generates one that serves them from a local stand-in instead of the network, every request is
+//! redirected to it, so the actor, parsers and pipeline run offline and deterministically, the uri parsers see
+//! is the original one. A request is matched on its method, uri and body, one not in the cassette is warned
+//! and gets 404. The plain `dyer run` generates the usual main again.
+//!
+//! `[package.metadata.dyer.headers]` in `Cargo.toml` gives the headers, such as `user-agent`, the generated main
+//! sets on every request without them, those set by the actor or the hooks are kept.
 //!
 //! ## dyer build
 //!
@@ -70,15 +144,138 @@
 //! ## dyer clean
 //!
 //! A wraper of `cargo clean`,   clean the directory.
+//!
+//! ## dyer gen
+//!
+//! Alias `dyer expand`, regenerate the main in `src/bin/` without building, `dyer gen --stdout` prints it
+//! instead, `dyer gen --check` exits with 1 if the main on disk is not the one generated from the current
+//! sources(the log level aside), for use in pre-commit hooks and CI.
+//!
+//! The generated main has user regions, `imports` after the `use` lines, `before-run` and `after-run` around
+//! `app.run(..)`, whose content is kept when it is regenerated, for custom setup such as metrics or tuning
+//! `App`. The rest of the main is not supposed to be edited, a main modified outside of its regions is not
+//! overwritten, `dyer gen --force` discards the changes.
+//!
+//! A project standardises its main with `templates/main.rs.tpl` instead of the built-in one, using the same
+//! placeholders, `<+entities+>`, `<+actor+>`, `<+marker_expr+>`, `<+get_middleware_map+>`, `<+get_pipeline_map+>`,
+//! `<+ctype+>`, `<+get_pkg_list+>`, `<+package_name+>`, the regions `<+region_imports+>`, `<+region_before-run+>`
+//! and `<+region_after-run+>`, along with `<+runtime+>`(`#[tokio::main]`), `<+handle_signals_call+>` and
+//! `<+handle_signals+>`(empty without the `signal` feature of tokio), `<+cassette_call+>` and `<+cassette+>`(empty
+//! without `--record` or `--replay`), `<+wrapped_hooks+>`(the middleware hooks wrapped for the cassette,
+//! `serve` and `headers`), `<+grace_period+>`, `<+log_level+>`, `<+config_path+>`,
+//! `<+bin_name+>`, `<+dyer_version+>` and `<+var.key+>` for every `key` in `[package.metadata.dyer.vars]` of
+//! `Cargo.toml`. A placeholder unknown is an error.
+//!
+//! ## dyer watch
+//!
+//! `dyer watch [check|test|run]` watches `src/`, `Cargo.toml` and `dyer.cfg`, and re-runs the cargo command(`check`
+//! as default) once they stay unchanged for a moment after changes, the main is rewritten only when the annotated
+//! items change. A running crawler is stopped with SIGTERM and shuts down gracefully before it is restarted.
+//!
+//! ## dyer list
+//!
+//! Alias `dyer ls`, list the actor, the entities with their variants, the parsers, and the hooks of middleware and
+//! pipeline discovered in the library, each with its file and line, followed by warnings about the items the
+//! generated main ignores or fails with, such as an unknown hook or two functions bound to the same hook.
+//! `dyer list --json` prints the same as json.
+//!
+//! ## dyer doctor
+//!
+//! Check the health of the project and print how to fix each problem found: the manifest(the `[[bin]]` path
+//! in `src/bin/`, the versions of dyer and tokio working together, `grace-period`), `src/bin` and the modules
+//! of library, the keys and values of `dyer.cfg`, the annotated items(the actor, `#[dyer::entity(entities)]`,
+//! an initializer returning `Option<..>`, unknown hooks) and the toolchain. It exits with 1 if any error is found.
+//!
+//! ## dyer test-parser
+//!
+//! Exercise the parsers without crawling. The bodies of responses saved in `tests/fixtures/<parser>/`, such as
+//! `tests/fixtures/parse_quote/page1.html`, are fed into the parser `<parser>` one by one, and what it returns,
+//! the entities(which must implement `Serialize`), the uri of tasks and the number of the others, is serialised
+//! as json and compared with the snapshot `page1.expected.json` next to the body. `dyer test-parser <parser>`
+//! tests only the one, `--update` writes the snapshots instead of comparing. `page1.head` saved by `dyer fetch`
+//! gives the response its uri, status and headers. The harness is generated as `tests/dyer_parsers.rs`, and
+//! `serde_json` is added to the dependencies if missing.
+//!
+//! ## dyer serve
+//!
+//! Develop parsers and tune the rate against a local copy of the site instead of hammering it. `dyer serve <dir>`
+//! serves the saved pages in `<dir>` on `http://127.0.0.1:8000`(`--port <port>` another one), `/a/b` from
+//! `<dir>/a/b` or `<dir>/a/b/index.html`. `serve.toml` in `<dir>` gives how a page is served, read on every request:
+//! ```toml
+//! ["/old"]
+//! redirect = "/new/"
+//! status = 301
+//!
+//! ["/api/records?page=2"]
+//! file = "page2.json"
+//! delay = 1.5
+//! header.retry-after = "10"
+//!
+//! ["/private/*"]
+//! status = 403
+//! ```
+//! A page is matched by its path with the query, then without, then by the longest prefix ending with `*`.
+//!
+//! `serve = "http://127.0.0.1:8000"` of `[package.metadata.dyer]` in `Cargo.toml` points the crawler at it, the
+//! generated main sends every request there keeping its path and query, the uri parsers see is the original one.
+//! `dyer serve` inside the project listens on the port given there.
+//!
+//! ## dyer fetch
+//!
+//! Save a page as a fixture of `dyer test-parser`. `dyer fetch <uri> --as <parser>` requests it as the generated
+//! main does, with the headers of `[package.metadata.dyer.headers]` and the body not encoded, and saves the body
+//! into `tests/fixtures/<parser>/`, its extension after the content type, along with `<case>.head` of the uri,
+//! the status line and the headers. The case is named after the last segment of path and the query, or
+//! `--name <case>`. `--parse` runs the parser on it and prints what it returns. Uri of https is fetched with
+//! `curl`.
+//!
+//! ## dyer shell
+//!
+//! Try selectors on a saved response. `dyer shell <fixture-or-file>` loads a file, or a fixture of
+//! `dyer test-parser` as `parse_quote/page1`(or `page1` if only one parser has it), and reads commands:
+//! `css <selector>`(with `::text` or `::attr(name)`), `xpath <expr>`, `re <pattern>` and `json <pointer>`
+//! print the matches as the generated parser takes them, `keep <field>` keeps the last one as a field,
+//! `parser [name]` prints a `#[dyer::parser]` taking the fields with `scraper`, `res.xpath()`, `regex` and
+//! `serde_json`, `help` lists the commands. The parser is printed on `quit` too if the fields changed since.
+//! Commands can be piped in, eg. `echo 'css h1::text' | dyer shell page.html`.
+//!
+//! ## dyer upgrade
+//!
+//! Migrate the project to a newer dyer, `dyer upgrade --to 3.3`(the latest supported as default). It bumps the
+//! dependencies in `Cargo.toml`, rewrites the known API changes in the files of annotated items, regenerates
+//! the main, and reports the usages that need manual attention with their file and line.
 
+mod feature;
+mod kind;
+mod select;
 mod subcommand;
+mod template;
 mod util;
 
+use subcommand::run::Cassette;
 use subcommand::{
-    SubComBuild, SubComCheck, SubComClean, SubComFix, SubComNew, SubComRun, SubComTest, SubCommand,
+    SubComBuild, SubComCheck, SubComClean, SubComDoctor, SubComFetch, SubComFix, SubComGen,
+    SubComInit, SubComList, SubComNew, SubComRun, SubComServe, SubComShell, SubComTest,
+    SubComTestParser, SubComUpgrade, SubComWatch, SubCommand,
 };
 use util::LogLevel;
 
+/// options of dyer-cli that take a value, `--key value` is stored as `--key=value`
+const VALUED_OPTIONS: [&str; 12] = [
+    "--template",
+    "--var",
+    "--kind",
+    "--name",
+    "--vcs",
+    "--dyer-version",
+    "--features",
+    "--to",
+    "--record",
+    "--replay",
+    "--port",
+    "--as",
+];
+
 #[derive(std::fmt::Debug)]
 pub struct Info {
     sub_command: String,
@@ -90,15 +287,17 @@ impl From<Vec<String>> for Info {
         let sub_command = args.remove(0);
         let mut options = Vec::new();
         let mut others = Vec::new();
-        args.into_iter().for_each(|item: String| {
-            if item.contains("--") {
-                options.push(item);
-            } else if item.contains("-") {
+        let mut args = args.into_iter();
+        while let Some(item) = args.next() {
+            if VALUED_OPTIONS.contains(&item.as_str()) {
+                let value = args.next().unwrap_or_default();
+                options.push(format!("{}={}", item, value));
+            } else if item.starts_with('-') {
                 options.push(item);
             } else {
                 others.push(item);
             }
-        });
+        }
         Info {
             sub_command,
             options,
@@ -106,51 +305,190 @@ impl From<Vec<String>> for Info {
         }
     }
 }
-impl Into<SubCommand> for Info {
-    fn into(mut self) -> SubCommand {
+impl From<Info> for SubCommand {
+    fn from(mut info: Info) -> Self {
         let mut comd: SubCommand = SubCommand::Null;
-        if self.sub_command == "new" {
-            let name = self.others.pop().expect("project name must be specified.");
-            let level = if self.options.is_empty() {
-                Some(LogLevel::Info)
+        if info.sub_command == "new" {
+            let list_templates = info.options.contains(&"--list-templates".to_string());
+            let path = if list_templates {
+                String::new()
             } else {
-                let index = self.options.pop().unwrap();
-                Some(index.parse::<LogLevel>().unwrap_or(LogLevel::Info))
+                info.others.pop().expect("project name must be specified.")
             };
+            let name = util::get_option_values(&info.options, "--name")
+                .pop()
+                .unwrap_or_else(|| {
+                    let path = path.trim_end_matches(['/', '\\']);
+                    path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
+                });
+            let level = info
+                .options
+                .iter()
+                .find_map(|op| op.parse::<LogLevel>().ok())
+                .unwrap_or(LogLevel::Info);
+            let template = util::get_option_values(&info.options, "--template").pop();
+            let kind = util::get_option_values(&info.options, "--kind").pop();
+            let vars = util::get_option_values(&info.options, "--var")
+                .into_iter()
+                .map(|var| match var.split_once('=') {
+                    Some((key, value)) => (key.to_string(), value.to_string()),
+                    None => (var, "true".to_string()),
+                })
+                .collect();
             comd = SubCommand::SubComNew(SubComNew {
+                path,
                 name,
-                option: level,
+                option: Some(level),
+                template,
+                kind,
+                vars,
+                list_templates,
+                force: info.options.contains(&"--force".to_string()),
+                vcs: util::get_option_values(&info.options, "--vcs").pop(),
+                dyer_version: util::get_option_values(&info.options, "--dyer-version").pop(),
+                features: util::get_option_values(&info.options, "--features").pop(),
+                interactive: info.options.contains(&"-i".to_string()),
+                no_affix: info.options.contains(&"--no-affix".to_string()),
             });
-        } else if ["run".into(), "r".into()].contains(&self.sub_command) {
+        } else if info.sub_command == "init" {
+            let path = info.others.pop().unwrap_or_else(|| ".".into());
+            let level = info
+                .options
+                .iter()
+                .find_map(|op| op.parse::<LogLevel>().ok())
+                .unwrap_or(LogLevel::Info);
+            comd = SubCommand::SubComInit(SubComInit {
+                path,
+                option: Some(level),
+                name: util::get_option_values(&info.options, "--name").pop(),
+            });
+        } else if ["run".into(), "r".into()].contains(&info.sub_command) {
+            let record = util::get_option_values(&info.options, "--record").pop();
+            let replay = util::get_option_values(&info.options, "--replay").pop();
+            let cassette = match (record, replay) {
+                (Some(_), Some(_)) => {
+                    util::exit_with("`--record` and `--replay` cannot be used together")
+                }
+                (Some(dir), _) | (_, Some(dir)) if dir.is_empty() => {
+                    util::exit_with("`--record` and `--replay` take the directory of cassette")
+                }
+                (Some(dir), None) => Some(Cassette::Record(dir)),
+                (None, Some(dir)) => Some(Cassette::Replay(dir)),
+                (None, None) => None,
+            };
             let item = SubComRun {
-                options: self.options,
+                options: info
+                    .options
+                    .into_iter()
+                    .filter(|op| !op.starts_with("--record=") && !op.starts_with("--replay="))
+                    .collect(),
+                cassette,
             };
             comd = SubCommand::SubComRun(item);
-        } else if self.sub_command == "fix" {
+        } else if info.sub_command == "fix" {
             let item = SubComFix {
-                options: self.options,
+                options: info.options,
             };
             comd = SubCommand::SubComFix(item);
-        } else if ["c".into(), "check".into()].contains(&self.sub_command) {
+        } else if ["c".into(), "check".into()].contains(&info.sub_command) {
             let item = SubComCheck {
-                options: self.options,
+                options: info.options,
             };
             comd = SubCommand::SubComCheck(item);
-        } else if ["b".into(), "build".into()].contains(&self.sub_command) {
+        } else if ["b".into(), "build".into()].contains(&info.sub_command) {
             let item = SubComBuild {
-                options: self.options,
+                options: info.options,
             };
             comd = SubCommand::SubComBuild(item);
-        } else if ["t".into(), "test".into()].contains(&self.sub_command) {
+        } else if ["t".into(), "test".into()].contains(&info.sub_command) {
             let item = SubComTest {
-                options: self.options,
+                options: info.options,
             };
             comd = SubCommand::SubComTest(item);
-        } else if ["clean".into()].contains(&self.sub_command) {
+        } else if ["clean".into()].contains(&info.sub_command) {
             let item = SubComClean {
-                options: self.options,
+                options: info.options,
             };
             comd = SubCommand::SubComClean(item);
+        } else if info.sub_command == "upgrade" {
+            let item = SubComUpgrade {
+                to: util::get_option_values(&info.options, "--to").pop(),
+            };
+            comd = SubCommand::SubComUpgrade(item);
+        } else if ["gen".into(), "expand".into()].contains(&info.sub_command) {
+            let item = SubComGen {
+                stdout: info.options.contains(&"--stdout".to_string()),
+                check: info.options.contains(&"--check".to_string()),
+                force: info.options.contains(&"--force".to_string()),
+            };
+            comd = SubCommand::SubComGen(item);
+        } else if info.sub_command == "watch" {
+            let item = SubComWatch {
+                command: info
+                    .others
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| "check".into()),
+                options: info.options,
+            };
+            comd = SubCommand::SubComWatch(item);
+        } else if ["list".into(), "ls".into()].contains(&info.sub_command) {
+            let item = SubComList {
+                json: info.options.contains(&"--json".to_string()),
+            };
+            comd = SubCommand::SubComList(item);
+        } else if info.sub_command == "doctor" {
+            comd = SubCommand::SubComDoctor(SubComDoctor {});
+        } else if info.sub_command == "test-parser" {
+            let item = SubComTestParser {
+                name: info.others.pop(),
+                update: info.options.contains(&"--update".to_string()),
+                show: None,
+                options: info
+                    .options
+                    .into_iter()
+                    .filter(|op| op != "--update")
+                    .collect(),
+            };
+            comd = SubCommand::SubComTestParser(item);
+        } else if info.sub_command == "serve" {
+            let item = SubComServe {
+                dir: info.others.pop().unwrap_or_else(|| {
+                    util::exit_with(
+                        "the directory of saved pages must be specified, eg. `dyer serve pages`",
+                    )
+                }),
+                port: util::get_option_values(&info.options, "--port").pop(),
+            };
+            comd = SubCommand::SubComServe(item);
+        } else if info.sub_command == "fetch" {
+            let example = "eg. `dyer fetch https://example.com/ --as parse_index`";
+            let item = SubComFetch {
+                uri: info.others.pop().unwrap_or_else(|| {
+                    util::exit_with(&format!("the uri to fetch must be specified, {}", example))
+                }),
+                parser: util::get_option_values(&info.options, "--as")
+                    .pop()
+                    .filter(|parser| !parser.is_empty())
+                    .unwrap_or_else(|| {
+                        util::exit_with(&format!(
+                            "the parser the page is saved for must be given by `--as <parser>`, {}",
+                            example
+                        ))
+                    }),
+                name: util::get_option_values(&info.options, "--name").pop(),
+                parse: info.options.contains(&"--parse".to_string()),
+            };
+            comd = SubCommand::SubComFetch(item);
+        } else if info.sub_command == "shell" {
+            let item = SubComShell {
+                target: info.others.pop().unwrap_or_else(|| {
+                    util::exit_with(
+                        "the response to load must be specified, a file or a fixture, eg. `dyer shell parse_quote/page1`",
+                    )
+                }),
+            };
+            comd = SubCommand::SubComShell(item);
         }
         comd
     }
@@ -160,8 +498,8 @@ fn main() {
     let mut args: Vec<String> = std::env::args().collect();
     //println!("raw arguments: {:?}", args);
     args.remove(0); // remove the unnecessary path
-    let msgs = "Handy tool for dyer\n\nUSAGE:\n\tdyer [subcommand] [options]\n\teg. dyer new myproject --debug create a project with logger level INFO\n\nSUBCOMMAND:\n\tnew:\t\tinitialize a new empty project\n\tcheck:\t a wraper of `cargo check`\n\tfix:\t\ta wraper of `cargo fix`\n\trun:\t\ta wraper of `cargo run`, compile and run the project\n\tbuild:\t a wraper of `cargo build`\n\ttest:\t  a wraper of `cargo test`\n\tclean:\t a wraper of `cargo clean`\n\nOPTIONS:\n\tall options of `cargo SUBCOMMAND`\n\t--off:\t\t  set the log level as Off\n\t--error:\t\tset the log level as ERROR\n\t--warn: \t\tset the log level as WARN\n\t--info: \t\tset the log level as INFO\n\t--debug:\t\tset the debug level as DEBUG\n\t--trace:\t\tset the log level as TRACE".replace("\t", "   ");
-    if args.len() > 0 && !["-h", "--help"].contains(&args[0].as_str()) {
+    let msgs = "Handy tool for dyer\n\nUSAGE:\n\tdyer [subcommand] [options]\n\teg. dyer new myproject --debug create a project with logger level INFO\n\nSUBCOMMAND:\n\tnew:\t\tinitialize a new empty project, `--template <path|name>` renders a template instead, `--var key=value` sets its placeholder, `--kind <kind>` starts from a built-in crawler(sitemap, paginated-list, json-api, login-session), `--list-templates` lists kinds and installed templates, `--force` overwrites a non-empty directory, `--name <name>` names the package other than the directory, `--vcs git|none` initializes a git repository(default when git is available) or not, `--dyer-version <x.y>` depends on another supported version of dyer(3.3, 3.2), `--features a,b` enables features of dyer, `-i` selects them interactively, `--no-affix` leaves out the affix\n\tinit:\t\tturn the current directory or an existing cargo crate into a dyer project, adding only the missing files\n\tcheck:\t a wraper of `cargo check`\n\tfix:\t\ta wraper of `cargo fix`\n\trun:\t\ta wraper of `cargo run`, compile and run the project, `--record <dir>` saves the responses into a cassette in <dir>, `--replay <dir>` serves them from it instead of the network\n\tbuild:\t a wraper of `cargo build`\n\ttest:\t  a wraper of `cargo test`\n\tclean:\t a wraper of `cargo clean`\n\tgen:\t\talias `expand`, regenerate the main in src/bin/ only, `--stdout` prints it instead, `--check` fails if it is out of date, `--force` overwrites the changes outside of user regions\n\twatch:\t\tre-run `check`(default), `test` or `run` when the sources change, regenerating the main if needed\n\tlist:\t\talias `ls`, list the actor, entities, parsers, middleware and pipeline hooks with their file:line and the hooks ignored, `--json` prints json\n\tdoctor:\t check the manifest, dyer.cfg, the annotated items and the toolchain, with the fix of each problem\n\ttest-parser:\trun the parsers(or the one named) against the responses saved in tests/fixtures/<parser>/ and compare with the snapshots, `--update` overwrites the snapshots\n\tserve:\t\tserve a directory of saved pages on localhost, `--port <port>` the port(8000 as default), with the status, headers, delay and redirect of pages in its serve.toml\n\tfetch:\t\tsave a page into tests/fixtures/<parser>/ with its status and headers, `--as <parser>` the parser it is saved for, `--name <case>` the name of case(taken from the uri as default), `--parse` runs the parser on it\n\tshell:\t\ttry css, xpath, regex and json pointer on a saved response or a fixture interactively, and print a `#[dyer::parser]` taking the fields kept\n\tupgrade:\tmigrate the project to a newer dyer, `--to <x.y>` the version(the latest supported as default)\n\nOPTIONS:\n\tall options of `cargo SUBCOMMAND`\n\t--off:\t\t  set the log level as Off\n\t--error:\t\tset the log level as ERROR\n\t--warn: \t\tset the log level as WARN\n\t--info: \t\tset the log level as INFO\n\t--debug:\t\tset the debug level as DEBUG\n\t--trace:\t\tset the log level as TRACE".replace("\t", "   ");
+    if !args.is_empty() && !["-h", "--help"].contains(&args[0].as_str()) {
         let sub_command: SubCommand = Info::from(args.clone()).into();
         //println!("parsed info: {:?}", sub_command);
         if let SubCommand::Null = sub_command {
@@ -172,14 +510,7 @@ fn main() {
         } else {
             sub_command.execute();
         }
-    } else if args.len() == 0 {
-        println!("{}", msgs);
-    } else if args.len() > 0 && ["-h", "--help"].contains(&args[0].as_str()) {
-        println!("{}", msgs);
     } else {
-        println!(
-            "Unknow arguments: \"{}\". Use `dyer -h` to see help",
-            args.join(" ")
-        );
+        println!("{}", msgs);
     }
 }
@@ -0,0 +1,1856 @@
+//! the selectors tried by `dyer shell` on a saved response
+//!
+//! html is parsed leniently into a tree, on which a subset of css(with `::text` and `::attr(name)`
+//! as scrapy does) and a subset of xpath 1.0 are evaluated, json is read for json pointers. They
+//! match what `scraper`, libxml and serde_json take in the generated parser on common pages, not
+//! on every corner of the specifications.
+
+use crate::util::json_str;
+
+/// elements that have neither content nor end tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+/// elements whose content is text until their end tag
+const RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "textarea", "title"];
+/// elements starting inside an open `p` close it
+const P_CLOSERS: [&str; 20] = [
+    "p",
+    "div",
+    "ul",
+    "ol",
+    "dl",
+    "table",
+    "form",
+    "pre",
+    "blockquote",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "nav",
+    "aside",
+    "hr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+];
+
+#[derive(std::fmt::Debug)]
+enum Data {
+    Document,
+    Element {
+        name: String,
+        attrs: Vec<(String, String)>,
+    },
+    Text(String),
+    Comment(String),
+}
+
+#[derive(std::fmt::Debug)]
+struct Node {
+    data: Data,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// the tree of html, the nodes are in document order with the document at 0
+#[derive(std::fmt::Debug)]
+pub(crate) struct Html {
+    nodes: Vec<Node>,
+}
+
+impl Html {
+    pub(crate) fn parse(buf: &str) -> Html {
+        let mut html = Html {
+            nodes: vec![Node {
+                data: Data::Document,
+                parent: None,
+                children: Vec::new(),
+            }],
+        };
+        let mut stack = vec![0];
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let parent = *stack.last().unwrap();
+            if let Some(after) = rest.strip_prefix("<!--") {
+                let end = after.find("-->").unwrap_or(after.len());
+                html.append(parent, Data::Comment(after[..end].to_string()));
+                rest = after.get(end + 3..).unwrap_or_default();
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+                rest = &rest[end..];
+            } else if rest.starts_with("</") {
+                let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+                let name = rest[2..end]
+                    .trim_end_matches('>')
+                    .trim()
+                    .to_ascii_lowercase();
+                if let Some(pos) = stack.iter().rposition(|id| html.name(*id) == Some(&name)) {
+                    stack.truncate(pos.max(1));
+                }
+                rest = &rest[end..];
+            } else if let Some((name, attrs, closed, len)) = parse_start_tag(rest) {
+                rest = &rest[len..];
+                while stack.len() > 1 {
+                    let open = html.name(*stack.last().unwrap()).unwrap_or_default();
+                    if !is_closed_by(open, &name) {
+                        break;
+                    }
+                    stack.pop();
+                }
+                let parent = *stack.last().unwrap();
+                let id = html.append(
+                    parent,
+                    Data::Element {
+                        name: name.clone(),
+                        attrs,
+                    },
+                );
+                if closed || VOID_ELEMENTS.contains(&name.as_str()) {
+                    continue;
+                }
+                if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                    let end = rest
+                        .to_ascii_lowercase()
+                        .find(&format!("</{}", name))
+                        .unwrap_or(rest.len());
+                    let text = match name.as_str() {
+                        "title" | "textarea" => decode_entities(&rest[..end]),
+                        _ => rest[..end].to_string(),
+                    };
+                    if !text.is_empty() {
+                        html.append(id, Data::Text(text));
+                    }
+                    rest = &rest[end..];
+                }
+                stack.push(id);
+            } else {
+                // a `<` not starting a tag is text
+                let skip = usize::from(rest.starts_with('<'));
+                let end = rest[skip..].find('<').map_or(rest.len(), |end| end + skip);
+                html.append_text(parent, &decode_entities(&rest[..end]));
+                rest = &rest[end..];
+            }
+        }
+        html
+    }
+
+    fn append(&mut self, parent: usize, data: Data) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            data,
+            parent: Some(parent),
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(id);
+        id
+    }
+
+    /// text next to text is joined
+    fn append_text(&mut self, parent: usize, text: &str) {
+        if let Some(last) = self.nodes[parent].children.last().copied() {
+            if let Data::Text(prev) = &mut self.nodes[last].data {
+                prev.push_str(text);
+                return;
+            }
+        }
+        self.append(parent, Data::Text(text.to_string()));
+    }
+
+    fn name(&self, id: usize) -> Option<&str> {
+        match &self.nodes[id].data {
+            Data::Element { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    fn attrs(&self, id: usize) -> &[(String, String)] {
+        match &self.nodes[id].data {
+            Data::Element { attrs, .. } => attrs,
+            _ => &[],
+        }
+    }
+
+    fn attr(&self, id: usize, key: &str) -> Option<&str> {
+        self.attrs(id)
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_element(&self, id: usize) -> bool {
+        self.name(id).is_some()
+    }
+
+    /// the element children of the parent of `id`, `id` included
+    fn element_siblings(&self, id: usize) -> Vec<usize> {
+        match self.nodes[id].parent {
+            Some(parent) => self.nodes[parent]
+                .children
+                .iter()
+                .copied()
+                .filter(|child| self.is_element(*child))
+                .collect(),
+            None => vec![id],
+        }
+    }
+
+    /// the nodes under `id` in document order, walked with a stack as documents nest deeply
+    fn descendants(&self, id: usize, found: &mut Vec<usize>) {
+        let mut stack = self.nodes[id].children.iter().rev().collect::<Vec<_>>();
+        while let Some(child) = stack.pop() {
+            found.push(*child);
+            stack.extend(self.nodes[*child].children.iter().rev());
+        }
+    }
+
+    /// the text inside, as `text()` of scraper and `get_content()` of libxml
+    pub(crate) fn text(&self, id: usize) -> String {
+        match &self.nodes[id].data {
+            Data::Text(text) | Data::Comment(text) => text.clone(),
+            _ => {
+                let mut found = Vec::new();
+                self.descendants(id, &mut found);
+                found
+                    .into_iter()
+                    .filter_map(|id| match &self.nodes[id].data {
+                        Data::Text(text) => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// the html of the node and its content
+    pub(crate) fn outer_html(&self, id: usize) -> String {
+        let mut buf = String::new();
+        // a node to write or, once its content is written, the end tag of an element
+        let mut stack = vec![Ok(id)];
+        while let Some(next) = stack.pop() {
+            let id = match next {
+                Ok(id) => id,
+                Err(name) => {
+                    buf.push_str(&format!("</{}>", name));
+                    continue;
+                }
+            };
+            match &self.nodes[id].data {
+                Data::Document => {
+                    stack.extend(self.nodes[id].children.iter().rev().map(|c| Ok(*c)))
+                }
+                Data::Text(text) => {
+                    let raw = self.nodes[id]
+                        .parent
+                        .and_then(|parent| self.name(parent))
+                        .is_some_and(|parent| RAW_TEXT_ELEMENTS[..2].contains(&parent));
+                    match raw {
+                        true => buf.push_str(text),
+                        false => buf.push_str(
+                            &text
+                                .replace('&', "&amp;")
+                                .replace('<', "&lt;")
+                                .replace('>', "&gt;"),
+                        ),
+                    }
+                }
+                Data::Comment(text) => buf.push_str(&format!("<!--{}-->", text)),
+                Data::Element { name, attrs } => {
+                    buf.push_str(&format!("<{}", name));
+                    for (key, value) in attrs.iter() {
+                        buf.push_str(&format!(
+                            " {}=\"{}\"",
+                            key,
+                            value.replace('&', "&amp;").replace('"', "&quot;")
+                        ));
+                    }
+                    buf.push('>');
+                    if VOID_ELEMENTS.contains(&name.as_str()) {
+                        continue;
+                    }
+                    stack.push(Err(name.as_str()));
+                    stack.extend(self.nodes[id].children.iter().rev().map(|c| Ok(*c)));
+                }
+            }
+        }
+        buf
+    }
+}
+
+/// an element `open` is closed by the start tag `start` without its end tag
+fn is_closed_by(open: &str, start: &str) -> bool {
+    match open {
+        "p" => P_CLOSERS.contains(&start) || ["h5", "h6"].contains(&start),
+        "li" => start == "li",
+        "dt" | "dd" => start == "dt" || start == "dd",
+        "td" | "th" => ["td", "th", "tr"].contains(&start),
+        "tr" => start == "tr",
+        "option" => start == "option",
+        _ => false,
+    }
+}
+
+/// the name, attributes, whether it closes itself and the length of a start tag
+type StartTag = (String, Vec<(String, String)>, bool, usize);
+
+/// the start tag at the beginning of `buf`
+fn parse_start_tag(buf: &str) -> Option<StartTag> {
+    let bytes = buf.as_bytes();
+    if bytes.len() < 2 || bytes[0] != b'<' || !bytes[1].is_ascii_alphabetic() {
+        return None;
+    }
+    let is_space = |b: u8| b.is_ascii_whitespace();
+    let mut i = 1;
+    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || b"-_:".contains(&bytes[i])) {
+        i += 1;
+    }
+    let name = buf[1..i].to_ascii_lowercase();
+    let mut attrs: Vec<(String, String)> = Vec::new();
+    loop {
+        while i < bytes.len() && is_space(bytes[i]) {
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => return Some((name, attrs, false, bytes.len())),
+            Some(b'>') => return Some((name, attrs, false, i + 1)),
+            Some(b'/') if bytes.get(i + 1) == Some(&b'>') => {
+                return Some((name, attrs, true, i + 2))
+            }
+            Some(b'/') | Some(b'=') => i += 1,
+            Some(_) => {
+                let start = i;
+                while i < bytes.len() && !is_space(bytes[i]) && !b"=>/".contains(&bytes[i]) {
+                    i += 1;
+                }
+                let key = buf[start..i].to_ascii_lowercase();
+                while i < bytes.len() && is_space(bytes[i]) {
+                    i += 1;
+                }
+                let mut value = String::new();
+                if bytes.get(i) == Some(&b'=') {
+                    i += 1;
+                    while i < bytes.len() && is_space(bytes[i]) {
+                        i += 1;
+                    }
+                    match bytes.get(i) {
+                        Some(quote) if *quote == b'"' || *quote == b'\'' => {
+                            let end = buf[i + 1..]
+                                .find(*quote as char)
+                                .map_or(bytes.len(), |end| i + 1 + end);
+                            value = decode_entities(&buf[i + 1..end]);
+                            i = (end + 1).min(bytes.len());
+                        }
+                        _ => {
+                            let start = i;
+                            while i < bytes.len() && !is_space(bytes[i]) && bytes[i] != b'>' {
+                                i += 1;
+                            }
+                            value = decode_entities(&buf[start..i]);
+                        }
+                    }
+                }
+                if !attrs.iter().any(|(k, _)| *k == key) {
+                    attrs.push((key, value));
+                }
+            }
+        }
+    }
+}
+
+/// the text with character references replaced
+fn decode_entities(buf: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = buf;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let c = reference.and_then(|name| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "hellip" => Some('…'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            _ => {
+                let code = match name.strip_prefix('#') {
+                    Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16),
+                    Some(dec) => dec.parse::<u32>(),
+                    None => return None,
+                };
+                code.ok().and_then(char::from_u32)
+            }
+        });
+        match (c, reference) {
+            (Some(c), Some(name)) => {
+                decoded.push(c);
+                rest = &rest[name.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// what is taken of each element matched by css
+#[derive(std::fmt::Debug, Clone, PartialEq)]
+pub(crate) enum Take {
+    Html,
+    Text,
+    Attr(String),
+}
+
+#[derive(std::fmt::Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+    Adjacent,
+    Sibling,
+}
+
+#[derive(std::fmt::Debug, Default)]
+struct Compound {
+    tag: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    // the name, and the operator with the value if any
+    attrs: Vec<(String, Option<(String, String)>)>,
+    // `first-child`, `last-child`, `only-child` or the position of `nth-child`
+    positions: Vec<(&'static str, usize)>,
+}
+
+/// a css selector with an optional pseudo-element `::text` or `::attr(name)`
+#[derive(std::fmt::Debug)]
+pub(crate) struct Css {
+    // the selector without the pseudo-element, as `scraper` takes it
+    pub(crate) selector: String,
+    pub(crate) take: Take,
+    // the compounds of each selector in the group, each with the combinator before it
+    groups: Vec<Vec<(Combinator, Compound)>>,
+}
+
+impl Css {
+    pub(crate) fn parse(selector: &str) -> Result<Css, String> {
+        let mut groups = Vec::new();
+        let mut takes = Vec::new();
+        let mut selectors = Vec::new();
+        for group in split_top_level(selector, ',') {
+            let group = group.trim();
+            let (group, take) = match group.find("::") {
+                Some(pos) => {
+                    let take = match &group[pos + 2..] {
+                        "text" => Take::Text,
+                        pseudo => pseudo
+                            .strip_prefix("attr(")
+                            .and_then(|attr| attr.strip_suffix(')'))
+                            .map(|attr| Take::Attr(attr.trim().to_ascii_lowercase()))
+                            .ok_or_else(|| {
+                                format!(
+                                    "`::{}` is not supported, use `::text` or `::attr(name)`",
+                                    pseudo
+                                )
+                            })?,
+                    };
+                    (group[..pos].trim(), take)
+                }
+                None => (group, Take::Html),
+            };
+            if group.is_empty() {
+                return Err(format!("the selector `{}` is empty", selector.trim()));
+            }
+            groups.push(parse_complex(group)?);
+            takes.push(take);
+            selectors.push(group.to_string());
+        }
+        let take = takes.pop().unwrap_or(Take::Html);
+        if takes.iter().any(|t| *t != take) {
+            return Err("the selectors separated by `,` must take the same pseudo-element".into());
+        }
+        Ok(Css {
+            selector: selectors.join(", "),
+            take,
+            groups,
+        })
+    }
+
+    /// what is taken of the matched elements in document order
+    pub(crate) fn select(&self, html: &Html) -> Vec<String> {
+        (0..html.nodes.len())
+            .filter(|id| html.is_element(*id))
+            .filter(|id| {
+                self.groups
+                    .iter()
+                    .any(|parts| matches_complex(html, *id, parts))
+            })
+            .filter_map(|id| match &self.take {
+                Take::Html => Some(html.outer_html(id)),
+                Take::Text => Some(html.text(id).trim().to_string()),
+                Take::Attr(attr) => html.attr(id, attr).map(|value| value.to_string()),
+            })
+            .collect()
+    }
+}
+
+/// the pieces of `buf` separated by `sep` outside of brackets, parentheses and quotes
+fn split_top_level(buf: &str, sep: char) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let (mut depth, mut quote, mut start) = (0, None, 0);
+    for (i, c) in buf.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '[') | (None, '(') => depth += 1,
+            (None, ']') | (None, ')') => depth -= 1,
+            (None, c) if c == sep && depth == 0 => {
+                pieces.push(&buf[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&buf[start..]);
+    pieces
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+fn parse_complex(selector: &str) -> Result<Vec<(Combinator, Compound)>, String> {
+    let chars = selector.chars().collect::<Vec<char>>();
+    let ident = |i: &mut usize| {
+        let start = *i;
+        while *i < chars.len() && is_ident_char(chars[*i]) {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect::<String>()
+    };
+    let mut parts = Vec::new();
+    let mut combinator = Combinator::Descendant;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '>' | '+' | '~' => {
+                combinator = match chars[i] {
+                    '>' => Combinator::Child,
+                    '+' => Combinator::Adjacent,
+                    _ => Combinator::Sibling,
+                };
+                i += 1;
+            }
+            _ => {
+                let mut compound = Compound::default();
+                if chars[i] == '*' {
+                    i += 1;
+                } else if is_ident_char(chars[i]) {
+                    compound.tag = Some(ident(&mut i).to_ascii_lowercase());
+                }
+                while i < chars.len() {
+                    match chars[i] {
+                        '#' => {
+                            i += 1;
+                            compound.ids.push(ident(&mut i));
+                        }
+                        '.' => {
+                            i += 1;
+                            compound.classes.push(ident(&mut i));
+                        }
+                        '[' => {
+                            let end = chars[i..]
+                                .iter()
+                                .position(|c| *c == ']')
+                                .map(|end| i + end)
+                                .ok_or_else(|| format!("`[` is not closed in `{}`", selector))?;
+                            let inner = chars[i + 1..end].iter().collect::<String>();
+                            compound.attrs.push(parse_attr_selector(&inner)?);
+                            i = end + 1;
+                        }
+                        ':' => {
+                            i += 1;
+                            let name = ident(&mut i);
+                            let position = match name.as_str() {
+                                "first-child" | "last-child" | "only-child" => 0,
+                                "nth-child" if chars.get(i) == Some(&'(') => {
+                                    let end = chars[i..]
+                                        .iter()
+                                        .position(|c| *c == ')')
+                                        .map_or(chars.len(), |end| i + end);
+                                    let n = chars[i + 1..end.min(chars.len())]
+                                        .iter()
+                                        .collect::<String>();
+                                    i = (end + 1).min(chars.len());
+                                    n.trim().parse::<usize>().map_err(|_| {
+                                        format!("`:nth-child({})` takes a position only", n)
+                                    })?
+                                }
+                                _ => return Err(format!("`:{}` is not supported", name)),
+                            };
+                            let name = ["first-child", "last-child", "only-child", "nth-child"]
+                                .iter()
+                                .find(|n| **n == name)
+                                .unwrap();
+                            compound.positions.push((name, position));
+                        }
+                        _ => break,
+                    }
+                }
+                let empty = compound.tag.is_none()
+                    && compound.ids.is_empty()
+                    && compound.classes.is_empty()
+                    && compound.attrs.is_empty()
+                    && compound.positions.is_empty();
+                if empty && chars.get(i.saturating_sub(1)) != Some(&'*') {
+                    return Err(format!("unexpected `{}` in `{}`", chars[i], selector));
+                }
+                parts.push((combinator, compound));
+                combinator = Combinator::Descendant;
+            }
+        }
+    }
+    if parts.is_empty() {
+        return Err(format!("nothing is selected by `{}`", selector));
+    }
+    Ok(parts)
+}
+
+/// `name`, or `name<op>value` with op one of `=`, `~=`, `^=`, `$=`, `*=` and `|=`
+fn parse_attr_selector(inner: &str) -> Result<(String, Option<(String, String)>), String> {
+    let pos = match inner.find('=') {
+        Some(pos) => pos,
+        None => return Ok((inner.trim().to_ascii_lowercase(), None)),
+    };
+    let (name, op) = match inner[..pos].chars().last() {
+        Some(c) if "~^$*|".contains(c) => (&inner[..pos - 1], format!("{}=", c)),
+        _ => (&inner[..pos], "=".to_string()),
+    };
+    let value = inner[pos + 1..].trim();
+    let value = match value.chars().next() {
+        Some(q) if (q == '"' || q == '\'') && value.len() > 1 && value.ends_with(q) => {
+            &value[1..value.len() - 1]
+        }
+        _ => value,
+    };
+    if name.trim().is_empty() {
+        return Err(format!("`[{}]` has no attribute", inner));
+    }
+    Ok((
+        name.trim().to_ascii_lowercase(),
+        Some((op, value.to_string())),
+    ))
+}
+
+fn matches_compound(html: &Html, id: usize, compound: &Compound) -> bool {
+    let name = match html.name(id) {
+        Some(name) => name,
+        None => return false,
+    };
+    if compound.tag.as_ref().is_some_and(|tag| tag != name) {
+        return false;
+    }
+    if !compound
+        .ids
+        .iter()
+        .all(|expected| html.attr(id, "id") == Some(expected))
+    {
+        return false;
+    }
+    let classes = html
+        .attr(id, "class")
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<&str>>();
+    if !compound
+        .classes
+        .iter()
+        .all(|class| classes.contains(&class.as_str()))
+    {
+        return false;
+    }
+    let attrs_match = compound.attrs.iter().all(|(key, test)| {
+        let value = match html.attr(id, key) {
+            Some(value) => value,
+            None => return false,
+        };
+        match test {
+            None => true,
+            Some((op, expected)) => match op.as_str() {
+                "=" => value == expected,
+                "~=" => value.split_whitespace().any(|v| v == expected),
+                "^=" => !expected.is_empty() && value.starts_with(expected.as_str()),
+                "$=" => !expected.is_empty() && value.ends_with(expected.as_str()),
+                "*=" => !expected.is_empty() && value.contains(expected.as_str()),
+                _ => value == expected || value.starts_with(&format!("{}-", expected)),
+            },
+        }
+    });
+    if !attrs_match {
+        return false;
+    }
+    let siblings = html.element_siblings(id);
+    let position = siblings.iter().position(|s| *s == id).unwrap_or(0) + 1;
+    compound.positions.iter().all(|(pseudo, n)| match *pseudo {
+        "first-child" => position == 1,
+        "last-child" => position == siblings.len(),
+        "only-child" => siblings.len() == 1,
+        _ => position == *n,
+    })
+}
+
+/// the element matches the last compound, and the elements around it match the rest
+fn matches_complex(html: &Html, id: usize, parts: &[(Combinator, Compound)]) -> bool {
+    let ((combinator, compound), rest) = match parts.split_last() {
+        Some(last) => last,
+        None => return true,
+    };
+    if !matches_compound(html, id, compound) {
+        return false;
+    }
+    if rest.is_empty() {
+        return true;
+    }
+    let siblings = html.element_siblings(id);
+    let position = siblings.iter().position(|s| *s == id).unwrap_or(0);
+    match combinator {
+        Combinator::Child => html.nodes[id]
+            .parent
+            .is_some_and(|parent| matches_complex(html, parent, rest)),
+        Combinator::Descendant => {
+            let mut ancestor = html.nodes[id].parent;
+            while let Some(a) = ancestor {
+                if matches_complex(html, a, rest) {
+                    return true;
+                }
+                ancestor = html.nodes[a].parent;
+            }
+            false
+        }
+        Combinator::Adjacent => position > 0 && matches_complex(html, siblings[position - 1], rest),
+        Combinator::Sibling => siblings[..position]
+            .iter()
+            .any(|s| matches_complex(html, *s, rest)),
+    }
+}
+
+/// a node selected by xpath: a node of the tree or an attribute of an element
+#[derive(std::fmt::Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum XNode {
+    Node(usize),
+    Attr(usize, usize),
+}
+
+#[derive(std::fmt::Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Parent,
+    Ancestor,
+    FollowingSibling,
+    PrecedingSibling,
+    SelfNode,
+    Attribute,
+}
+
+#[derive(std::fmt::Debug, Clone, PartialEq)]
+enum NodeTest {
+    Name(String),
+    Any,
+    Text,
+    Comment,
+    Node,
+}
+
+#[derive(std::fmt::Debug)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+#[derive(std::fmt::Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(&'static str, Box<Expr>, Box<Expr>),
+    Union(Vec<Expr>),
+    Literal(String),
+    Number(f64),
+    Call(String, Vec<Expr>),
+    // absolute or not, and the steps
+    Path(bool, Vec<Step>),
+    // the nodes in parentheses, the predicates on them and the steps after
+    Filter(Box<Expr>, Vec<Expr>, Vec<Step>),
+}
+
+#[derive(std::fmt::Debug)]
+enum Value {
+    Nodes(Vec<XNode>),
+    Str(String),
+    Number(f64),
+    Bool(bool),
+}
+
+#[derive(std::fmt::Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    Dot,
+    DoubleDot,
+    At,
+    Star,
+    Name(String),
+    Axis(String),
+    Literal(String),
+    Number(f64),
+    Operator(&'static str),
+    Pipe,
+    Comma,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+}
+
+impl std::fmt::Display for Token {
+    /// the token as written in the xpath
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Slash => write!(f, "/"),
+            Token::DoubleSlash => write!(f, "//"),
+            Token::Dot => write!(f, "."),
+            Token::DoubleDot => write!(f, ".."),
+            Token::At => write!(f, "@"),
+            Token::Star => write!(f, "*"),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Axis(axis) => write!(f, "{}::", axis),
+            Token::Literal(literal) => write!(f, "'{}'", literal),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Operator(op) => write!(f, "{}", op),
+            Token::Pipe => write!(f, "|"),
+            Token::Comma => write!(f, ","),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::LeftBracket => write!(f, "["),
+            Token::RightBracket => write!(f, "]"),
+        }
+    }
+}
+
+/// an xpath selecting nodes, whose string values are taken as `get_content()` of libxml does
+#[derive(std::fmt::Debug)]
+pub(crate) struct XPath {
+    expr: Expr,
+}
+
+impl XPath {
+    pub(crate) fn parse(xpath: &str) -> Result<XPath, String> {
+        let tokens = tokenize(xpath)?;
+        let mut parser = XPathParser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected `{}` in `{}`", token, xpath));
+        }
+        match expr {
+            Expr::Path(..) | Expr::Union(_) | Expr::Filter(..) => Ok(XPath { expr }),
+            _ => Err(format!(
+                "`{}` does not select nodes, which `res.xpath()` returns",
+                xpath
+            )),
+        }
+    }
+
+    /// the string values of the selected nodes in document order
+    pub(crate) fn select(&self, html: &Html) -> Vec<String> {
+        let context = Context {
+            node: XNode::Node(0),
+            position: 1,
+            size: 1,
+        };
+        match evaluate(html, &self.expr, &context) {
+            Value::Nodes(nodes) => nodes.iter().map(|n| string_value(html, *n)).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn tokenize(xpath: &str) -> Result<Vec<Token>, String> {
+    let chars = xpath.chars().collect::<Vec<char>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '/' if next == Some('/') => Token::DoubleSlash,
+            '/' => Token::Slash,
+            '.' if next == Some('.') => Token::DoubleDot,
+            '.' if !next.is_some_and(|n| n.is_ascii_digit()) => Token::Dot,
+            '@' => Token::At,
+            '*' => Token::Star,
+            '|' => Token::Pipe,
+            ',' => Token::Comma,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
+            '=' => Token::Operator("="),
+            '!' if next == Some('=') => Token::Operator("!="),
+            '<' if next == Some('=') => Token::Operator("<="),
+            '>' if next == Some('=') => Token::Operator(">="),
+            '<' => Token::Operator("<"),
+            '>' => Token::Operator(">"),
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|q| *q == c)
+                    .ok_or_else(|| format!("the string is not closed in `{}`", xpath))?;
+                let literal = chars[i + 1..i + 1 + end].iter().collect::<String>();
+                i += end + 2;
+                tokens.push(Token::Literal(literal));
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let number = chars[start..i].iter().collect::<String>();
+                tokens.push(Token::Number(number.parse().map_err(|_| {
+                    format!("`{}` is not a number in `{}`", number, xpath)
+                })?));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || "-_.".contains(chars[i])) {
+                    i += 1;
+                }
+                let name = chars[start..i].iter().collect::<String>();
+                if chars.get(i) == Some(&':') && chars.get(i + 1) == Some(&':') {
+                    i += 2;
+                    tokens.push(Token::Axis(name));
+                } else {
+                    tokens.push(Token::Name(name));
+                }
+                continue;
+            }
+            c => return Err(format!("unexpected `{}` in `{}`", c, xpath)),
+        };
+        i += match token {
+            Token::DoubleSlash | Token::DoubleDot => 2,
+            Token::Operator(op) => op.len(),
+            _ => 1,
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct XPathParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl XPathParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected `{}`, found `{}`", expected, token)),
+            None => Err(format!("expected `{}`, found the end", expected)),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Name("or".into())) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_compare()?;
+        while self.peek() == Some(&Token::Name("and".into())) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_compare()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_compare(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_union()?;
+        while let Some(Token::Operator(op)) = self.peek().cloned() {
+            self.pos += 1;
+            expr = Expr::Compare(op, Box::new(expr), Box::new(self.parse_union()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_union(&mut self) -> Result<Expr, String> {
+        let mut exprs = vec![self.parse_primary()?];
+        while self.peek() == Some(&Token::Pipe) {
+            self.pos += 1;
+            exprs.push(self.parse_primary()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.pop().unwrap(),
+            _ => Expr::Union(exprs),
+        })
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.peek().cloned() {
+            Some(Token::Literal(literal)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(literal))
+            }
+            Some(Token::Number(number)) => {
+                self.pos += 1;
+                Ok(Expr::Number(number))
+            }
+            Some(Token::LeftParen) => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                self.expect(Token::RightParen)?;
+                let predicates = self.parse_predicates()?;
+                let mut steps = Vec::new();
+                self.parse_steps(&mut steps)?;
+                match predicates.is_empty() && steps.is_empty() {
+                    true => Ok(expr),
+                    false => Ok(Expr::Filter(Box::new(expr), predicates, steps)),
+                }
+            }
+            Some(Token::Name(name))
+                if self.tokens.get(self.pos + 1) == Some(&Token::LeftParen)
+                    && !["text", "node", "comment"].contains(&name.as_str()) =>
+            {
+                self.pos += 2;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RightParen) {
+                    args.push(self.parse_or()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.parse_or()?);
+                    }
+                }
+                self.expect(Token::RightParen)?;
+                Ok(Expr::Call(name, args))
+            }
+            _ => self.parse_path(),
+        }
+    }
+
+    fn parse_path(&mut self) -> Result<Expr, String> {
+        let mut steps = Vec::new();
+        let absolute = matches!(self.peek(), Some(Token::Slash) | Some(Token::DoubleSlash));
+        let starts_step = |token: Option<&Token>| {
+            matches!(
+                token,
+                Some(Token::Dot)
+                    | Some(Token::DoubleDot)
+                    | Some(Token::At)
+                    | Some(Token::Star)
+                    | Some(Token::Name(_))
+                    | Some(Token::Axis(_))
+            )
+        };
+        if !absolute {
+            steps.push(self.parse_step()?);
+        } else if self.peek() == Some(&Token::Slash) && !starts_step(self.tokens.get(self.pos + 1))
+        {
+            // `/` alone is the document
+            self.pos += 1;
+            return Ok(Expr::Path(true, steps));
+        }
+        self.parse_steps(&mut steps)?;
+        Ok(Expr::Path(absolute, steps))
+    }
+
+    /// the steps each after `/` or `//`
+    fn parse_steps(&mut self, steps: &mut Vec<Step>) -> Result<(), String> {
+        loop {
+            match self.peek() {
+                Some(Token::Slash) => self.pos += 1,
+                Some(Token::DoubleSlash) => {
+                    self.pos += 1;
+                    steps.push(Step {
+                        axis: Axis::DescendantOrSelf,
+                        test: NodeTest::Node,
+                        predicates: Vec::new(),
+                    });
+                }
+                _ => break,
+            }
+            steps.push(self.parse_step()?);
+        }
+        Ok(())
+    }
+
+    fn parse_predicates(&mut self) -> Result<Vec<Expr>, String> {
+        let mut predicates = Vec::new();
+        while self.peek() == Some(&Token::LeftBracket) {
+            self.pos += 1;
+            predicates.push(self.parse_or()?);
+            self.expect(Token::RightBracket)?;
+        }
+        Ok(predicates)
+    }
+
+    fn parse_step(&mut self) -> Result<Step, String> {
+        let (axis, test) = match self.next() {
+            Some(Token::Dot) => (Axis::SelfNode, NodeTest::Node),
+            Some(Token::DoubleDot) => (Axis::Parent, NodeTest::Node),
+            Some(Token::At) => (Axis::Attribute, self.parse_test()?),
+            Some(Token::Axis(axis)) => {
+                let axis = match axis.as_str() {
+                    "child" => Axis::Child,
+                    "descendant" => Axis::Descendant,
+                    "descendant-or-self" => Axis::DescendantOrSelf,
+                    "parent" => Axis::Parent,
+                    "ancestor" => Axis::Ancestor,
+                    "following-sibling" => Axis::FollowingSibling,
+                    "preceding-sibling" => Axis::PrecedingSibling,
+                    "self" => Axis::SelfNode,
+                    "attribute" => Axis::Attribute,
+                    _ => return Err(format!("axis `{}` is not supported", axis)),
+                };
+                (axis, self.parse_test()?)
+            }
+            Some(Token::Star) | Some(Token::Name(_)) => {
+                self.pos -= 1;
+                (Axis::Child, self.parse_test()?)
+            }
+            Some(token) => return Err(format!("expected a step, found `{}`", token)),
+            None => {
+                return Err(match self.pos.checked_sub(2).map(|pos| &self.tokens[pos]) {
+                    Some(token @ Token::Slash) | Some(token @ Token::DoubleSlash) => {
+                        format!("the path ends with `{}`", token)
+                    }
+                    _ => "expected a step, found the end".into(),
+                })
+            }
+        };
+        let predicates = self.parse_predicates()?;
+        Ok(Step {
+            axis,
+            test,
+            predicates,
+        })
+    }
+
+    fn parse_test(&mut self) -> Result<NodeTest, String> {
+        match self.next() {
+            Some(Token::Star) => Ok(NodeTest::Any),
+            Some(Token::Name(name)) => {
+                if self.peek() != Some(&Token::LeftParen) {
+                    return Ok(NodeTest::Name(name.to_ascii_lowercase()));
+                }
+                self.pos += 1;
+                self.expect(Token::RightParen)?;
+                match name.as_str() {
+                    "text" => Ok(NodeTest::Text),
+                    "comment" => Ok(NodeTest::Comment),
+                    _ => Ok(NodeTest::Node),
+                }
+            }
+            Some(token) => Err(format!("expected a name, found `{}`", token)),
+            None => Err("expected a name, found the end".into()),
+        }
+    }
+}
+
+struct Context {
+    node: XNode,
+    position: usize,
+    size: usize,
+}
+
+fn string_value(html: &Html, node: XNode) -> String {
+    match node {
+        XNode::Node(id) => html.text(id),
+        XNode::Attr(id, index) => html.attrs(id)[index].1.clone(),
+    }
+}
+
+/// the nodes on the axis in proximity order
+fn axis_nodes(html: &Html, node: XNode, axis: Axis) -> Vec<XNode> {
+    let id = match node {
+        XNode::Node(id) => id,
+        XNode::Attr(id, _) => {
+            return match axis {
+                Axis::Parent => vec![XNode::Node(id)],
+                Axis::SelfNode => vec![node],
+                Axis::Ancestor => {
+                    let mut found = vec![XNode::Node(id)];
+                    found.extend(axis_nodes(html, XNode::Node(id), Axis::Ancestor));
+                    found
+                }
+                _ => Vec::new(),
+            };
+        }
+    };
+    let siblings = || {
+        html.nodes[id]
+            .parent
+            .map(|parent| html.nodes[parent].children.clone())
+            .unwrap_or_default()
+    };
+    let ids = match axis {
+        Axis::Child => html.nodes[id].children.clone(),
+        Axis::Descendant | Axis::DescendantOrSelf => {
+            let mut found = match axis {
+                Axis::DescendantOrSelf => vec![id],
+                _ => Vec::new(),
+            };
+            html.descendants(id, &mut found);
+            found
+        }
+        Axis::Parent => html.nodes[id].parent.into_iter().collect(),
+        Axis::Ancestor => {
+            let mut found = Vec::new();
+            let mut parent = html.nodes[id].parent;
+            while let Some(p) = parent {
+                found.push(p);
+                parent = html.nodes[p].parent;
+            }
+            found
+        }
+        Axis::FollowingSibling => siblings().into_iter().filter(|s| *s > id).collect(),
+        Axis::PrecedingSibling => siblings().into_iter().filter(|s| *s < id).rev().collect(),
+        Axis::SelfNode => vec![id],
+        Axis::Attribute => {
+            return (0..html.attrs(id).len())
+                .map(|index| XNode::Attr(id, index))
+                .collect()
+        }
+    };
+    ids.into_iter().map(XNode::Node).collect()
+}
+
+fn matches_test(html: &Html, node: XNode, axis: Axis, test: &NodeTest) -> bool {
+    match (node, test) {
+        (XNode::Attr(id, index), NodeTest::Name(name)) => html.attrs(id)[index].0 == *name,
+        (XNode::Attr(..), NodeTest::Any) => true,
+        (XNode::Attr(..), NodeTest::Node) => axis == Axis::Attribute || axis == Axis::SelfNode,
+        (XNode::Attr(..), _) => false,
+        (XNode::Node(_), NodeTest::Node) => true,
+        (XNode::Node(id), NodeTest::Text) => matches!(html.nodes[id].data, Data::Text(_)),
+        (XNode::Node(id), NodeTest::Comment) => matches!(html.nodes[id].data, Data::Comment(_)),
+        (XNode::Node(id), NodeTest::Any) => html.is_element(id),
+        (XNode::Node(id), NodeTest::Name(name)) => html.name(id) == Some(name),
+    }
+}
+
+/// the nodes passing the predicates, each evaluated with the position in `nodes`
+fn filter_nodes(html: &Html, mut nodes: Vec<XNode>, predicates: &[Expr]) -> Vec<XNode> {
+    for predicate in predicates.iter() {
+        let size = nodes.len();
+        nodes = nodes
+            .into_iter()
+            .enumerate()
+            .filter(|(i, n)| {
+                let context = Context {
+                    node: *n,
+                    position: i + 1,
+                    size,
+                };
+                match evaluate(html, predicate, &context) {
+                    Value::Number(number) => number == (i + 1) as f64,
+                    value => to_bool(&value),
+                }
+            })
+            .map(|(_, n)| n)
+            .collect();
+    }
+    nodes
+}
+
+/// the nodes reached from `current` through the steps, in document order
+fn walk(html: &Html, mut current: Vec<XNode>, steps: &[Step]) -> Vec<XNode> {
+    for step in steps.iter() {
+        let mut next = Vec::new();
+        for node in current.iter() {
+            let candidates = axis_nodes(html, *node, step.axis)
+                .into_iter()
+                .filter(|n| matches_test(html, *n, step.axis, &step.test))
+                .collect::<Vec<XNode>>();
+            next.extend(filter_nodes(html, candidates, &step.predicates));
+        }
+        next.sort();
+        next.dedup();
+        current = next;
+    }
+    current
+}
+
+fn evaluate(html: &Html, expr: &Expr, context: &Context) -> Value {
+    match expr {
+        Expr::Literal(literal) => Value::Str(literal.clone()),
+        Expr::Number(number) => Value::Number(*number),
+        Expr::Path(absolute, steps) => {
+            let start = match absolute {
+                true => XNode::Node(0),
+                false => context.node,
+            };
+            Value::Nodes(walk(html, vec![start], steps))
+        }
+        Expr::Filter(expr, predicates, steps) => match evaluate(html, expr, context) {
+            Value::Nodes(nodes) => {
+                Value::Nodes(walk(html, filter_nodes(html, nodes, predicates), steps))
+            }
+            value => value,
+        },
+        Expr::Union(exprs) => {
+            let mut nodes = Vec::new();
+            for expr in exprs.iter() {
+                if let Value::Nodes(found) = evaluate(html, expr, context) {
+                    nodes.extend(found);
+                }
+            }
+            nodes.sort();
+            nodes.dedup();
+            Value::Nodes(nodes)
+        }
+        Expr::Or(a, b) => Value::Bool(
+            to_bool(&evaluate(html, a, context)) || to_bool(&evaluate(html, b, context)),
+        ),
+        Expr::And(a, b) => Value::Bool(
+            to_bool(&evaluate(html, a, context)) && to_bool(&evaluate(html, b, context)),
+        ),
+        Expr::Compare(op, a, b) => {
+            let (a, b) = (evaluate(html, a, context), evaluate(html, b, context));
+            Value::Bool(compare(html, op, &a, &b))
+        }
+        Expr::Call(name, args) => {
+            let arg = |i: usize| args.get(i).map(|arg| evaluate(html, arg, context));
+            let string = |i: usize| match arg(i) {
+                Some(value) => to_string(html, &value),
+                None => string_value(html, context.node),
+            };
+            match name.as_str() {
+                "last" => Value::Number(context.size as f64),
+                "position" => Value::Number(context.position as f64),
+                "count" => match arg(0) {
+                    Some(Value::Nodes(nodes)) => Value::Number(nodes.len() as f64),
+                    _ => Value::Number(0.0),
+                },
+                "not" => Value::Bool(!arg(0).is_some_and(|value| to_bool(&value))),
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "contains" => Value::Bool(string(0).contains(&string(1))),
+                "starts-with" => Value::Bool(string(0).starts_with(&string(1))),
+                "string" => Value::Str(string(0)),
+                "string-length" => Value::Number(string(0).chars().count() as f64),
+                "normalize-space" => {
+                    Value::Str(string(0).split_whitespace().collect::<Vec<_>>().join(" "))
+                }
+                "concat" => Value::Str((0..args.len()).map(string).collect()),
+                "name" | "local-name" => Value::Str(match context.node {
+                    XNode::Node(id) => html.name(id).unwrap_or_default().to_string(),
+                    XNode::Attr(id, index) => html.attrs(id)[index].0.clone(),
+                }),
+                _ => Value::Bool(false),
+            }
+        }
+    }
+}
+
+fn to_bool(value: &Value) -> bool {
+    match value {
+        Value::Nodes(nodes) => !nodes.is_empty(),
+        Value::Str(s) => !s.is_empty(),
+        Value::Number(n) => *n != 0.0 && !n.is_nan(),
+        Value::Bool(b) => *b,
+    }
+}
+
+fn to_string(html: &Html, value: &Value) -> String {
+    match value {
+        Value::Nodes(nodes) => nodes
+            .first()
+            .map(|n| string_value(html, *n))
+            .unwrap_or_default(),
+        Value::Str(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+    }
+}
+
+fn compare(html: &Html, op: &str, a: &Value, b: &Value) -> bool {
+    let test = |x: &str, y: &str| match op {
+        "=" => x == y,
+        "!=" => x != y,
+        _ => {
+            let (x, y) = match (x.trim().parse::<f64>(), y.trim().parse::<f64>()) {
+                (Ok(x), Ok(y)) => (x, y),
+                _ => return false,
+            };
+            match op {
+                "<" => x < y,
+                "<=" => x <= y,
+                ">" => x > y,
+                _ => x >= y,
+            }
+        }
+    };
+    let strings = |value: &Value| match value {
+        Value::Nodes(nodes) => nodes.iter().map(|n| string_value(html, *n)).collect(),
+        value => vec![to_string(html, value)],
+    };
+    match (a, b) {
+        (Value::Bool(_), _) | (_, Value::Bool(_)) => {
+            let (x, y) = (to_bool(a), to_bool(b));
+            match op {
+                "=" => x == y,
+                "!=" => x != y,
+                _ => false,
+            }
+        }
+        (Value::Number(n), other) | (other, Value::Number(n))
+            if !matches!(other, Value::Nodes(_)) =>
+        {
+            let x = match a {
+                Value::Number(_) => n.to_string(),
+                _ => to_string(html, other),
+            };
+            let y = match a {
+                Value::Number(_) => to_string(html, other),
+                _ => n.to_string(),
+            };
+            test(&x, &y)
+        }
+        _ => {
+            let (xs, ys) = (strings(a), strings(b));
+            xs.iter().any(|x| ys.iter().any(|y| test(x, y)))
+        }
+    }
+}
+
+/// json read with the order of keys kept
+#[derive(std::fmt::Debug)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn parse(buf: &str) -> Result<Json, String> {
+        let chars = buf.chars().collect::<Vec<char>>();
+        let mut i = 0;
+        let json = parse_json_value(&chars, &mut i, 0)?;
+        skip_whitespace(&chars, &mut i);
+        if i < chars.len() {
+            return Err(format!(
+                "unexpected `{}` at {} after the value",
+                chars[i], i
+            ));
+        }
+        Ok(json)
+    }
+
+    /// the value at the json pointer, as `Value::pointer` of serde_json
+    pub(crate) fn pointer(&self, pointer: &str) -> Result<Option<&Json>, String> {
+        if pointer.is_empty() {
+            return Ok(Some(self));
+        }
+        if !pointer.starts_with('/') {
+            return Err(format!(
+                "`{}` is not a json pointer, which starts with `/` such as `/data/0/name`",
+                pointer
+            ));
+        }
+        let mut current = self;
+        for token in pointer[1..].split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            let next = match current {
+                Json::Object(pairs) => pairs.iter().find(|(key, _)| *key == token).map(|(_, v)| v),
+                Json::Array(items) if token == "0" || !token.starts_with('0') => {
+                    token.parse::<usize>().ok().and_then(|i| items.get(i))
+                }
+                _ => None,
+            };
+            current = match next {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(current))
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::Str(s) => write!(f, "{}", json_str(s)),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(pairs) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{}", json_str(key), value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn skip_whitespace(chars: &[char], i: &mut usize) {
+    while *i < chars.len() && chars[*i].is_whitespace() {
+        *i += 1;
+    }
+}
+
+/// arrays and objects nested deeper are rejected, as `serde_json` does
+const MAX_JSON_DEPTH: usize = 128;
+
+fn parse_json_value(chars: &[char], i: &mut usize, depth: usize) -> Result<Json, String> {
+    skip_whitespace(chars, i);
+    if depth >= MAX_JSON_DEPTH && matches!(chars.get(*i), Some('[') | Some('{')) {
+        return Err(format!("nested deeper than {} at {}", MAX_JSON_DEPTH, i));
+    }
+    let unexpected = |i: usize| match chars.get(i) {
+        Some(c) => format!("unexpected `{}` at {}", c, i),
+        None => "unexpected end".to_string(),
+    };
+    let keyword = |i: &mut usize, word: &str, json: Json| {
+        let end = *i + word.len();
+        if chars
+            .get(*i..end)
+            .is_some_and(|w| w.iter().copied().eq(word.chars()))
+        {
+            *i = end;
+            Ok(json)
+        } else {
+            Err(unexpected(*i))
+        }
+    };
+    match chars.get(*i) {
+        Some('n') => keyword(i, "null", Json::Null),
+        Some('t') => keyword(i, "true", Json::Bool(true)),
+        Some('f') => keyword(i, "false", Json::Bool(false)),
+        Some('"') => parse_json_string(chars, i).map(Json::Str),
+        Some('[') => {
+            *i += 1;
+            let mut items = Vec::new();
+            skip_whitespace(chars, i);
+            if chars.get(*i) == Some(&']') {
+                *i += 1;
+                return Ok(Json::Array(items));
+            }
+            loop {
+                items.push(parse_json_value(chars, i, depth + 1)?);
+                skip_whitespace(chars, i);
+                match chars.get(*i) {
+                    Some(',') => *i += 1,
+                    Some(']') => {
+                        *i += 1;
+                        return Ok(Json::Array(items));
+                    }
+                    _ => return Err(unexpected(*i)),
+                }
+            }
+        }
+        Some('{') => {
+            *i += 1;
+            let mut pairs = Vec::new();
+            skip_whitespace(chars, i);
+            if chars.get(*i) == Some(&'}') {
+                *i += 1;
+                return Ok(Json::Object(pairs));
+            }
+            loop {
+                skip_whitespace(chars, i);
+                if chars.get(*i) != Some(&'"') {
+                    return Err(unexpected(*i));
+                }
+                let key = parse_json_string(chars, i)?;
+                skip_whitespace(chars, i);
+                if chars.get(*i) != Some(&':') {
+                    return Err(unexpected(*i));
+                }
+                *i += 1;
+                pairs.push((key, parse_json_value(chars, i, depth + 1)?));
+                skip_whitespace(chars, i);
+                match chars.get(*i) {
+                    Some(',') => *i += 1,
+                    Some('}') => {
+                        *i += 1;
+                        return Ok(Json::Object(pairs));
+                    }
+                    _ => return Err(unexpected(*i)),
+                }
+            }
+        }
+        Some(c) if *c == '-' || c.is_ascii_digit() => {
+            let start = *i;
+            while *i < chars.len() && (chars[*i].is_ascii_digit() || "+-.eE".contains(chars[*i])) {
+                *i += 1;
+            }
+            Ok(Json::Number(chars[start..*i].iter().collect()))
+        }
+        _ => Err(unexpected(*i)),
+    }
+}
+
+fn parse_json_string(chars: &[char], i: &mut usize) -> Result<String, String> {
+    *i += 1;
+    let mut s = String::new();
+    loop {
+        match chars.get(*i) {
+            None => return Err("the string is not closed".into()),
+            Some('"') => {
+                *i += 1;
+                return Ok(s);
+            }
+            Some('\\') => {
+                let escaped = chars.get(*i + 1).copied();
+                *i += 2;
+                match escaped {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex = chars
+                            .get(*i..*i + 4)
+                            .map(|h| h.iter().collect::<String>())
+                            .unwrap_or_default();
+                        let mut code = u32::from_str_radix(&hex, 16)
+                            .map_err(|_| format!("`\\u{}` is not a valid escape", hex))?;
+                        *i += 4;
+                        // a surrogate pair
+                        if (0xd800..0xdc00).contains(&code)
+                            && chars.get(*i) == Some(&'\\')
+                            && chars.get(*i + 1) == Some(&'u')
+                        {
+                            let low = chars
+                                .get(*i + 2..*i + 6)
+                                .map(|h| h.iter().collect::<String>())
+                                .and_then(|h| u32::from_str_radix(&h, 16).ok())
+                                .unwrap_or(0);
+                            if (0xdc00..0xe000).contains(&low) {
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                                *i += 6;
+                            }
+                        }
+                        s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    Some(c) => s.push(c),
+                    None => return Err("the string is not closed".into()),
+                }
+            }
+            Some(c) => {
+                s.push(*c);
+                *i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>Q &amp; A</title>
+<script>if (a < b) { document.write("<p>no</p>"); }</script></head>
+<body>
+<div id="list" class="quotes wide">
+  <p class="quote first"><span>one</span> &quot;1&quot;
+  <p class="quote"><span>two</span><br>2
+  <p class="quote last"><a href="/next?page=2&amp;q=x" data-n="3">three</a>
+</div>
+<!-- the end -->
+<ul><li>a<li>b</ul>
+</body></html>"#;
+
+    fn css(selector: &str) -> Vec<String> {
+        Css::parse(selector).unwrap().select(&Html::parse(PAGE))
+    }
+
+    fn xpath(xpath: &str) -> Vec<String> {
+        XPath::parse(xpath).unwrap().select(&Html::parse(PAGE))
+    }
+
+    #[test]
+    fn html_closes_implied_end_tags_and_keeps_raw_text() {
+        assert_eq!(
+            css("div > p"),
+            vec![
+                "<p class=\"quote first\"><span>one</span> \"1\"\n  </p>",
+                "<p class=\"quote\"><span>two</span><br>2\n  </p>",
+                "<p class=\"quote last\"><a href=\"/next?page=2&amp;q=x\" data-n=\"3\">three</a>\n</p>",
+            ]
+        );
+        assert_eq!(css("li"), vec!["<li>a</li>", "<li>b</li>"]);
+        assert_eq!(css("title::text"), vec!["Q & A"]);
+        assert_eq!(
+            css("script::text"),
+            vec!["if (a < b) { document.write(\"<p>no</p>\"); }"]
+        );
+    }
+
+    #[test]
+    fn css_matches_compounds_and_combinators() {
+        assert_eq!(css("p.quote.last a::text"), vec!["three"]);
+        assert_eq!(css("#list > .first span::text"), vec!["one"]);
+        assert_eq!(css("p.first + p span::text"), vec!["two"]);
+        assert_eq!(
+            css("p.first ~ p span::text, ul li:last-child::text"),
+            vec!["two", "b"]
+        );
+        assert_eq!(css("p:nth-child(2) span::text"), vec!["two"]);
+        assert_eq!(
+            css("div[class~=wide] > p:first-child span::text"),
+            vec!["one"]
+        );
+        assert_eq!(css("a[href^='/next']::attr(data-n)"), vec!["3"]);
+        assert_eq!(css("a::attr(href)"), vec!["/next?page=2&q=x"]);
+        assert_eq!(css("p::attr(id)"), Vec::<String>::new());
+        assert_eq!(css("p.first::text"), vec!["one \"1\""]);
+    }
+
+    #[test]
+    fn css_rejects_what_scraper_cannot_take() {
+        assert!(Css::parse("p::before").is_err());
+        assert!(Css::parse("p:hover").is_err());
+        assert!(Css::parse("a[href").is_err());
+        assert!(Css::parse("p::text, a::attr(href)").is_err());
+        assert!(Css::parse("").is_err());
+        assert_eq!(Css::parse(" p ::text").unwrap().selector, "p");
+    }
+
+    #[test]
+    fn xpath_selects_nodes_as_libxml() {
+        assert_eq!(
+            xpath("//p[@class='quote last']/a/@href"),
+            vec!["/next?page=2&q=x"]
+        );
+        assert_eq!(xpath("//div/p[2]/span/text()"), vec!["two"]);
+        assert_eq!(xpath("//p[last()]/a"), vec!["three"]);
+        assert_eq!(xpath("//p[contains(@class, 'first')]//span"), vec!["one"]);
+        assert_eq!(xpath("//li | //span"), vec!["one", "two", "a", "b"]);
+        assert_eq!(xpath("(//li)[1]"), vec!["a"]);
+        assert_eq!(xpath("//comment()"), vec![" the end "]);
+        assert_eq!(xpath("//a/../preceding-sibling::p[1]/span"), vec!["two"]);
+        assert_eq!(xpath("//ul[count(li) = 2]/li[2]"), vec!["b"]);
+        assert_eq!(xpath("//p[@id]"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn xpath_rejects_what_does_not_select_nodes() {
+        assert!(XPath::parse("count(//p)").is_err());
+        assert!(XPath::parse("//p[").is_err());
+        assert!(XPath::parse("//p[@class='x]").is_err());
+        assert!(XPath::parse("//p]").is_err());
+    }
+
+    #[test]
+    fn xpath_errors_name_the_token() {
+        let error = |xpath: &str| XPath::parse(xpath).unwrap_err();
+        assert_eq!(error(""), "expected a step, found the end");
+        assert_eq!(error("("), "expected a step, found the end");
+        assert_eq!(error("//*["), "expected a step, found the end");
+        assert_eq!(error("//p/"), "the path ends with `/`");
+        assert_eq!(error("//p[1"), "expected `]`, found the end");
+        assert_eq!(error("//p[)]"), "expected a step, found `)`");
+        assert_eq!(error("//p]"), "unexpected `]` in `//p]`");
+        assert_eq!(error("//@]"), "expected a name, found `]`");
+    }
+
+    #[test]
+    fn json_reads_pointers_as_serde_json() {
+        let json =
+            Json::parse(r#" {"data": [{"name": "a\"b", "n": -1.5e3}, null], "a/b": {"~": true}} "#)
+                .unwrap();
+        let at = |pointer: &str| json.pointer(pointer).unwrap().map(|v| v.to_string());
+        assert_eq!(at("/data/0/name").as_deref(), Some("\"a\\\"b\""));
+        assert_eq!(at("/data/0/n").as_deref(), Some("-1.5e3"));
+        assert_eq!(at("/data/1").as_deref(), Some("null"));
+        assert_eq!(at("/a~1b/~0").as_deref(), Some("true"));
+        assert_eq!(at("/data/01"), None);
+        assert_eq!(at("/data/2"), None);
+        assert_eq!(
+            at("").as_deref(),
+            Some(r#"{"data":[{"name":"a\"b","n":-1.5e3},null],"a/b":{"~":true}}"#)
+        );
+        assert!(json.pointer("data").is_err());
+    }
+
+    #[test]
+    fn json_rejects_invalid_and_deeply_nested_documents() {
+        assert!(Json::parse("{\"a\": 1,}").is_err());
+        assert!(Json::parse("[1] 2").is_err());
+        assert!(Json::parse("tru").is_err());
+        assert!(Json::parse(&"[".repeat(MAX_JSON_DEPTH)).is_err());
+        let nested = format!(
+            "{}{}",
+            "[".repeat(MAX_JSON_DEPTH),
+            "]".repeat(MAX_JSON_DEPTH)
+        );
+        assert!(Json::parse(&nested).is_ok());
+        let nested = format!(
+            "{}{}",
+            "[".repeat(MAX_JSON_DEPTH + 1),
+            "]".repeat(MAX_JSON_DEPTH + 1)
+        );
+        assert!(Json::parse(&nested).unwrap_err().contains("nested deeper"));
+        assert!(Json::parse(&"[".repeat(200_000)).is_err());
+    }
+}
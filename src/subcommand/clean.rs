@@ -1,4 +1,4 @@
-use crate::subcommand::run::MetaData;
+use crate::subcommand::run;
 use crate::util;
 
 #[derive(std::fmt::Debug)]
@@ -8,24 +8,7 @@ pub struct SubComClean {
 
 impl SubComClean {
     pub fn execute(&self) {
-        let paths = std::fs::read_dir("./src/bin")
-            .unwrap()
-            .map(|p| p.unwrap().path().to_str().unwrap().into())
-            .collect::<Vec<String>>();
-        //println!("files in \"./\" {:?}", paths);
-        let pkg_name = util::get_package_name() + ".rs";
-        let mut meta = MetaData::new();
-        meta.init();
-        if !meta.hash().0
-            || !paths
-                .iter()
-                .fold(false, |acc, x| acc || x.contains(&pkg_name))
-        {
-            let mut meta = MetaData::new();
-            meta.init();
-            //println!("{:?}", meta);
-            meta.make_main();
-        }
+        run::ensure_main(None);
         let options = self
             .options
             .iter()
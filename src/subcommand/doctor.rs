@@ -0,0 +1,472 @@
+use crate::subcommand::list;
+use crate::subcommand::run::{self, MetaData};
+use crate::util;
+
+/// keys of `dyer.cfg` that dyer reads, with the type of their values
+const CONFIG_KEYS: [(&str, &str); 23] = [
+    ("arg_affix.is_on", "bool"),
+    ("arg_affix.affix_min", "usize"),
+    ("arg_affix.affix_max", "usize"),
+    ("rate.cycle", "f64"),
+    ("rate.interval", "f64"),
+    ("rate.load", "f64"),
+    ("rate.remains", "usize"),
+    ("rate.rate_low", "f64"),
+    // written by `dyer new` though dyer does not read it
+    ("rate.err", "usize"),
+    ("data_dir", "path"),
+    ("skip", "bool"),
+    ("nap", "f64"),
+    ("join_gap", "f64"),
+    ("round_req", "usize"),
+    ("round_req_min", "usize"),
+    ("round_req_max", "usize"),
+    ("buf_task", "usize"),
+    ("spawn_task_max", "usize"),
+    ("round_task", "usize"),
+    ("round_task_min", "usize"),
+    ("round_res", "usize"),
+    ("round_errs", "usize"),
+    ("round_entity", "usize"),
+];
+
+// dyer doctor
+#[derive(std::fmt::Debug)]
+pub struct SubComDoctor {}
+
+/// a problem found and how to fix it
+#[derive(std::fmt::Debug)]
+struct Problem {
+    // the main cannot be generated or the project cannot run, a warning otherwise
+    error: bool,
+    message: String,
+    fix: String,
+}
+
+#[derive(std::fmt::Debug, Default)]
+struct Report {
+    problems: Vec<Problem>,
+}
+
+impl Report {
+    fn error(&mut self, message: String, fix: String) {
+        self.problems.push(Problem {
+            error: true,
+            message,
+            fix,
+        });
+    }
+
+    fn warning(&mut self, message: String, fix: String) {
+        self.problems.push(Problem {
+            error: false,
+            message,
+            fix,
+        });
+    }
+
+    fn has_error(&self) -> bool {
+        self.problems.iter().any(|p| p.error)
+    }
+}
+
+impl SubComDoctor {
+    pub fn execute(&self) {
+        let mut report = Report::default();
+        check_toolchain(&mut report);
+        match std::fs::read_to_string("Cargo.toml") {
+            Ok(manifest) => {
+                check_manifest(&manifest, &mut report);
+                check_files(&mut report);
+                check_config(&mut report);
+                check_modules(&mut report);
+            }
+            Err(_) => report.error(
+                "current directory contains no `Cargo.toml`".into(),
+                "run it at the root of the project, or `dyer init` to make this directory one"
+                    .into(),
+            ),
+        }
+        // discovering modules fails on the errors above, the main is checked only without them
+        if !report.has_error() {
+            check_main(&mut report);
+        }
+
+        let errors = report.problems.iter().filter(|p| p.error).count();
+        let warnings = report.problems.len() - errors;
+        for problem in report.problems.iter() {
+            let level = if problem.error { "error" } else { "warning" };
+            println!("{}: {}", level, problem.message);
+            println!("    fix: {}", problem.fix);
+        }
+        if report.problems.is_empty() {
+            println!("no problems found");
+            return;
+        }
+        println!("{} error(s), {} warning(s)", errors, warnings);
+        if errors > 0 {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// cargo and rustc are there to build the project
+fn check_toolchain(report: &mut Report) {
+    for tool in ["cargo", "rustc"].iter() {
+        let found = std::process::Command::new(tool)
+            .arg("--version")
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+        if !found {
+            report.error(
+                format!("`{}` cannot be run", tool),
+                "install the rust toolchain with rustup and add it to PATH".into(),
+            );
+        }
+    }
+}
+
+fn check_manifest(manifest: &str, report: &mut Report) {
+    if util::get_manifest_value(manifest, "lib", "name")
+        .or_else(|| util::get_manifest_value(manifest, "package", "name"))
+        .is_none()
+    {
+        report.error(
+            "the name of package cannot be found in Cargo.toml".into(),
+            "set `name` of `[package]` in Cargo.toml".into(),
+        );
+    }
+    // the stem of generated main, the same pattern as `util::get_package_name`
+    let bin = regex::Regex::new(r"(?m)^\s*path\s*=.*?src/bin/(?P<stem>[\w|-]+)\.rs").unwrap();
+    if !bin.is_match(manifest) {
+        report.error(
+            "no `[[bin]]` in Cargo.toml has its path in `src/bin/`".into(),
+            "add `[[bin]]` with `path = \"src/bin/<name>.rs\"` to Cargo.toml, the main is generated there"
+                .into(),
+        );
+    }
+
+    let version = match util::get_dyer_dependency(manifest) {
+        Some(dependency) => match util::resolve_dyer_version(&dependency) {
+            Ok(version) => version,
+            Err(e) => {
+                report.error(
+                    e,
+                    format!(
+                        "depend on a supported version, eg. `dyer = \"{}\"`",
+                        util::DYER_VERSION
+                    ),
+                );
+                return;
+            }
+        },
+        None => {
+            report.error(
+                "the dependency of dyer cannot be found in `[dependencies]` of Cargo.toml".into(),
+                format!(
+                    "add `dyer = \"{}\"` to `[dependencies]`",
+                    util::DYER_VERSION
+                ),
+            );
+            return;
+        }
+    };
+    let expected = util::get_version_rule(version, "tokio");
+    let tokio = util::get_manifest_table(manifest, "dependencies")
+        .into_iter()
+        .find(|(key, _)| key == "tokio")
+        .map(|(_, value)| value);
+    let tokio = match tokio {
+        Some(tokio) => tokio,
+        None => {
+            report.error(
+                "tokio is not in `[dependencies]` of Cargo.toml, the generated main runs on it"
+                    .into(),
+                format!("add `tokio = {}` to `[dependencies]`", expected),
+            );
+            return;
+        }
+    };
+    // quotes of a plain version are removed already
+    let tokio_version = regex::Regex::new(r#"^\{.*?\bversion\s*=\s*"(?P<version>[^"]+)""#)
+        .unwrap()
+        .captures(&tokio)
+        .map_or(tokio.as_str(), |cap| cap.name("version").unwrap().as_str())
+        .trim_start_matches(['^', '~', '='])
+        .to_string();
+    // dyer 3.3 runs on tokio 1, 3.2 on tokio 0.2
+    let compatible = match version {
+        "3.2" => tokio_version == "0.2" || tokio_version.starts_with("0.2."),
+        _ => tokio_version == "1" || tokio_version.starts_with("1."),
+    };
+    if !compatible {
+        report.error(
+            format!(
+                "dyer {} does not work with tokio {}",
+                version, tokio_version
+            ),
+            format!("depend on `tokio = {}`", expected),
+        );
+    }
+    if !tokio.contains("\"signal\"") && !tokio.contains("\"full\"") {
+        report.warning(
+            "tokio has no feature `signal`, the generated main does not shut down gracefully on Ctrl+c or SIGTERM"
+                .into(),
+            format!("depend on `tokio = {}`", expected),
+        );
+    }
+    if let Some((_, grace)) = util::get_manifest_table(manifest, "package.metadata.dyer")
+        .into_iter()
+        .find(|(key, _)| key == "grace-period")
+    {
+        if grace.parse::<u64>().is_err() {
+            report.error(
+                format!("`grace-period` of `[package.metadata.dyer]` is `{}`", grace),
+                "set it in seconds, eg. `grace-period = 30`".into(),
+            );
+        }
+    }
+    if let Some((_, origin)) = util::get_manifest_table(manifest, "package.metadata.dyer")
+        .into_iter()
+        .find(|(key, _)| key == "serve")
+    {
+        let pat = regex::Regex::new(r"^http://[^/?#\s]+/?$").unwrap();
+        if !pat.is_match(&origin) {
+            report.error(
+                format!("`serve` of `[package.metadata.dyer]` is `{}`", origin),
+                "set it as the origin of `dyer serve`, eg. `serve = \"http://127.0.0.1:8000\"`"
+                    .into(),
+            );
+        }
+    }
+    if let Err(e) = util::get_configured_headers(manifest) {
+        report.error(
+            format!("`[package.metadata.dyer.headers]`: {}", e),
+            "set the headers as `user-agent = \"Mozilla/5.0 ...\"`, one per line".into(),
+        );
+    }
+}
+
+/// the directories and files the generated main needs
+fn check_files(report: &mut Report) {
+    if !std::path::Path::new("src/bin").is_dir() {
+        report.error(
+            "directory `src/bin` does not exist, the main is generated inside".into(),
+            "create it with `mkdir src/bin`".into(),
+        );
+    }
+    for module in run::MODULES.iter().filter(|m| **m != "affix") {
+        let path = format!("src/{}.rs", module);
+        if !std::path::Path::new(&path).exists() {
+            report.error(
+                format!(
+                    "{} does not exist, the {} is discovered there",
+                    path, module
+                ),
+                format!(
+                    "create {}, `dyer new` of another project shows what goes inside",
+                    path
+                ),
+            );
+        }
+    }
+}
+
+/// `dyer.cfg` read by `App` on start
+fn check_config(report: &mut Report) {
+    let buf = match std::fs::read_to_string("dyer.cfg") {
+        Ok(buf) => buf,
+        Err(_) => {
+            report.error(
+                "dyer.cfg does not exist, the app panics on start without it".into(),
+                "create dyer.cfg, `dyer new` of another project generates the default one".into(),
+            );
+            return;
+        }
+    };
+    for (i, line) in buf.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim().trim_end_matches(',').trim()),
+            None => {
+                report.warning(
+                    format!("dyer.cfg:{}: `{}` is ignored", i + 1, line),
+                    "write it as `key: value,`".into(),
+                );
+                continue;
+            }
+        };
+        let typ = match CONFIG_KEYS.iter().find(|(k, _)| *k == key) {
+            Some((_, typ)) => *typ,
+            None => {
+                report.warning(
+                    format!("dyer.cfg:{}: unknown key `{}` is ignored", i + 1, key),
+                    "remove it or correct the spelling".into(),
+                );
+                continue;
+            }
+        };
+        let valid = match typ {
+            "bool" => value.parse::<bool>().is_ok(),
+            "usize" => value.parse::<usize>().is_ok(),
+            "f64" => value.parse::<f64>().is_ok(),
+            _ => !value.is_empty(),
+        };
+        if !valid {
+            report.error(
+                format!(
+                    "dyer.cfg:{}: `{}` of `{}` is not a valid {}",
+                    i + 1,
+                    value,
+                    key,
+                    typ
+                ),
+                format!("set `{}` to a {}", key, typ),
+            );
+        }
+    }
+}
+
+/// the annotated items the main is generated from
+fn check_modules(report: &mut Report) {
+    let meta = MetaData::new();
+    let mut items = Vec::new();
+    let mut sources = Vec::new();
+    for path in meta.get_inputs().iter().filter(|p| p.ends_with(".rs")) {
+        let buf = std::fs::read_to_string(path).unwrap_or_default();
+        items.extend(run::get_items(path, &buf));
+        sources.push((path.clone(), buf));
+    }
+    let found = |module: &str| {
+        items
+            .iter()
+            .filter(|item| item.module == module && item.path == format!("src/{}.rs", module))
+            .collect::<Vec<_>>()
+    };
+
+    match found("actor").len() {
+        0 => report.error(
+            "no `#[dyer::actor]` struct is found in src/actor.rs".into(),
+            "mark the struct implementing `Actor` with `#[dyer::actor]`".into(),
+        ),
+        1 => {}
+        _ => report.warning(
+            "more than one `#[dyer::actor]` is found in src/actor.rs, any of them may be used"
+                .into(),
+            "keep `#[dyer::actor]` on the actor to run only".into(),
+        ),
+    }
+    if !found("entity")
+        .iter()
+        .any(|item| item.key.as_deref() == Some("entities") && item.typ == "enum")
+    {
+        report.error(
+            "no `#[dyer::entity(entities)]` enum is found in src/entity.rs".into(),
+            "mark the enum containing all entities with `#[dyer::entity(entities)]`".into(),
+        );
+    }
+    let initializers = found("pipeline")
+        .into_iter()
+        .filter(|item| item.key.as_deref() == Some("initializer"))
+        .collect::<Vec<_>>();
+    match initializers.last() {
+        None => report.error(
+            "no `#[dyer::pipeline(initializer)]` is found in src/pipeline.rs, the type of `PipeLine` comes from it"
+                .into(),
+            "add an initializer returning `Option<T>`, eg. `Option<std::fs::File>`".into(),
+        ),
+        Some(item) => {
+            let buf = &sources.iter().find(|(p, _)| *p == item.path).unwrap().1;
+            let signature = buf
+                .lines()
+                .skip(item.line - 1)
+                .collect::<Vec<&str>>()
+                .join("\n");
+            let signature = signature.split('{').next().unwrap_or_default();
+            let returns = regex::Regex::new(r"->\s*Option\s*<").unwrap();
+            if !returns.is_match(signature) {
+                report.error(
+                    format!(
+                        "{}:{}: initializer `{}` does not return `Option<..>`",
+                        item.path, item.line, item.ident
+                    ),
+                    "return `Option<T>` with `T` a complete path, eg. `Option<std::fs::File>`"
+                        .into(),
+                );
+            }
+        }
+    }
+
+    let has_affix = std::path::Path::new("src/affix.rs").exists();
+    for warning in list::get_warnings(&items, has_affix) {
+        let message = format!("{}:{}: {}", warning.path, warning.line, warning.message);
+        if warning.fatal {
+            report.error(message, "correct or remove the attribute".into());
+        } else {
+            report.warning(
+                message,
+                "run `dyer list` to see the wiring of the project".into(),
+            );
+        }
+    }
+}
+
+/// the hooks have the signatures dyer expects, and the main can be regenerated without losing changes
+fn check_main(report: &mut Report) {
+    let mut meta = MetaData::new();
+    if let Err(e) = meta.try_init() {
+        report.error(
+            e,
+            "correct the project so that its items can be found".into(),
+        );
+        return;
+    }
+    let problems = meta.check_hooks();
+    if !problems.is_empty() {
+        for problem in problems {
+            report.error(
+                problem,
+                "name one of the valid hooks in the attribute".into(),
+            );
+        }
+        return;
+    }
+    let mismatches = meta.check_signatures();
+    if !mismatches.is_empty() {
+        for mismatch in mismatches {
+            report.error(
+                mismatch,
+                "change the function to the signature dyer expects".into(),
+            );
+        }
+        return;
+    }
+    if let Err(e) = meta.render_main() {
+        let fix = if e.contains(run::MAIN_TEMPLATE_PATH) {
+            format!(
+                "correct {} as above, or remove it to generate the main from the built-in template",
+                run::MAIN_TEMPLATE_PATH
+            )
+        } else {
+            "correct the item or the file named above".into()
+        };
+        report.error(e, fix);
+        return;
+    }
+    // a main not generated yet has nothing to lose
+    let main = match std::fs::read_to_string(meta.get_main_path()) {
+        Ok(main) => main,
+        Err(_) => return,
+    };
+    if let Err(e) = meta.check_unmodified(&main) {
+        report.error(
+            e,
+            "move the changes into the user regions, or discard them with `dyer gen --force`"
+                .into(),
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use crate::subcommand::run::MetaData;
+use crate::subcommand::test_parser::{SubComTestParser, FIXTURES};
+use crate::util;
+use std::io::{Read, Write};
+
+/// seconds to wait for the server
+const TIMEOUT: u64 = 30;
+
+// dyer fetch <uri> --as <parser> [--name <case>] [--parse]
+#[derive(std::fmt::Debug)]
+pub struct SubComFetch {
+    pub uri: String,
+    // the parser the page is saved for
+    pub parser: String,
+    // the name of the case, taken from the uri if `None`
+    pub name: Option<String>,
+    // run the parser on the page once it is saved
+    pub parse: bool,
+}
+
+/// the response as received, the body joined if it is sent in chunks
+#[derive(std::fmt::Debug)]
+struct Fetched {
+    status_line: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Fetched {
+    fn status(&self) -> &str {
+        self.status_line.split_whitespace().nth(1).unwrap_or("")
+    }
+
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl SubComFetch {
+    pub fn execute(&self) {
+        let mut meta = MetaData::new();
+        meta.init();
+        let parsers = meta.get_parsers();
+        if !parsers.contains(&self.parser) {
+            util::exit_with(&format!(
+                "no `#[dyer::parser]` named `{}` is found in src/parser.rs, parsers: {}",
+                self.parser,
+                parsers.join(", ")
+            ));
+        }
+        let pat = regex::Regex::new(r"^[\w-]+$").unwrap();
+        if let Some(name) = self.name.as_ref().filter(|name| !pat.is_match(name)) {
+            util::exit_with(&format!(
+                "`{}` cannot name a case, use letters, digits, `_` and `-` only",
+                name
+            ));
+        }
+
+        let headers = get_request_headers(&meta.headers);
+        let fetched = if self.uri.starts_with("http://") {
+            fetch_plainly(&self.uri, &headers)
+        } else if self.uri.starts_with("https://") {
+            fetch_with_curl(&self.uri, &headers)
+        } else {
+            util::exit_with(&format!(
+                "`{}` is not a uri of http or https, eg. `https://example.com/`",
+                self.uri
+            ))
+        }
+        .unwrap_or_else(|e| util::exit_with(&format!("failed to fetch {}: {}", self.uri, e)));
+        println!(
+            "    fetch {} {} {}B",
+            self.uri,
+            fetched.status(),
+            fetched.body.len()
+        );
+        // dyer decodes the body before parsing, which is asked not to be encoded
+        if let Some(encoding) = fetched
+            .get_header("content-encoding")
+            .filter(|encoding| !encoding.eq_ignore_ascii_case("identity"))
+        {
+            util::exit_with(&format!(
+                "the body is encoded in `{}` though `identity` is asked, save it decoded by hand",
+                encoding
+            ));
+        }
+
+        let dir = format!("{}/{}", FIXTURES, self.parser);
+        std::fs::create_dir_all(&dir).unwrap();
+        let case = match self.name.as_ref() {
+            Some(name) => {
+                if !get_case_files(&dir, name).is_empty() {
+                    println!("    overwrite case `{}` in {}", name, dir);
+                }
+                name.clone()
+            }
+            None => get_case(&self.uri, &dir),
+        };
+        // a case has only one body
+        for path in get_case_files(&dir, &case) {
+            std::fs::remove_file(path).unwrap();
+        }
+        let body_path = format!(
+            "{}/{}.{}",
+            dir,
+            case,
+            get_extension(fetched.get_header("content-type").unwrap_or(""))
+        );
+        let head_path = format!("{}/{}.head", dir, case);
+        let mut head = format!("GET {}\n{}\n", self.uri, fetched.status_line);
+        for (name, value) in fetched.headers.iter() {
+            // the body is saved joined
+            if !name.eq_ignore_ascii_case("transfer-encoding") {
+                head.push_str(&format!("{}: {}\n", name, value));
+            }
+        }
+        std::fs::write(&body_path, &fetched.body).unwrap();
+        std::fs::write(&head_path, head).unwrap();
+        println!("    saved {} and {}", body_path, head_path);
+
+        if !self.parse {
+            println!(
+                "    run `dyer test-parser {} --update` to take the snapshot",
+                self.parser
+            );
+            return;
+        }
+        SubComTestParser {
+            name: Some(self.parser.clone()),
+            update: false,
+            show: Some(case),
+            options: Vec::new(),
+        }
+        .execute();
+    }
+}
+
+/// the headers sent as the generated main does, with those of `[package.metadata.dyer.headers]`,
+/// the body is asked not to be encoded
+fn get_request_headers(configured: &[(String, String)]) -> Vec<(String, String)> {
+    let mut headers = configured
+        .iter()
+        .filter(|(name, _)| !["host", "connection", "accept-encoding"].contains(&name.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    headers.push(("accept-encoding".into(), "identity".into()));
+    headers
+}
+
+/// fetch with a plain connection for uri of http
+fn fetch_plainly(uri: &str, headers: &[(String, String)]) -> Result<Fetched, String> {
+    let pat = regex::Regex::new(
+        r"^http://(?P<authority>(?P<host>\[[0-9A-Fa-f:.]+\]|[^/?#:\[\]]+)(:(?P<port>\d+))?)(?P<path>[^#]*)",
+    )
+    .unwrap();
+    let cap = pat
+        .captures(uri)
+        .ok_or_else(|| format!("`{}` is not a valid uri", uri))?;
+    let port = cap
+        .name("port")
+        .map_or(Ok(80), |port| port.as_str().parse::<u16>())
+        .map_err(|e| format!("invalid port: {}", e))?;
+    let path = match &cap["path"] {
+        "" => "/".to_string(),
+        path if path.starts_with('?') => format!("/{}", path),
+        path => path.to_string(),
+    };
+    // an ipv6 literal is bracketed in uri only
+    let host = cap["host"].trim_start_matches('[').trim_end_matches(']');
+    let mut stream = std::net::TcpStream::connect((host, port)).map_err(|e| e.to_string())?;
+    let timeout = Some(std::time::Duration::from_secs(TIMEOUT));
+    stream.set_read_timeout(timeout).unwrap();
+    stream.set_write_timeout(timeout).unwrap();
+    let mut request = format!("GET {} HTTP/1.1\r\nhost: {}\r\n", path, &cap["authority"]);
+    for (name, value) in headers.iter() {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("connection: close\r\n\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).map_err(|e| e.to_string())?;
+    parse_response(&raw)
+}
+
+/// fetch with curl for uri of https, the response is taken as it is sent
+fn fetch_with_curl(uri: &str, headers: &[(String, String)]) -> Result<Fetched, String> {
+    let timeout = TIMEOUT.to_string();
+    let mut args = vec![
+        "-sS".to_string(),
+        "--http1.1".into(),
+        "--raw".into(),
+        "-i".into(),
+        "--suppress-connect-headers".into(),
+        "--max-time".into(),
+        timeout,
+    ];
+    // the headers curl adds on its own are removed unless configured
+    for name in ["user-agent", "accept"].iter() {
+        if !headers.iter().any(|(key, _)| key == name) {
+            args.extend(["-H".to_string(), format!("{}:", name)]);
+        }
+    }
+    for (name, value) in headers.iter() {
+        args.extend(["-H".to_string(), format!("{}: {}", name, value)]);
+    }
+    args.push(uri.to_string());
+    let output = std::process::Command::new("curl")
+        .args(&args)
+        .output()
+        .map_err(|e| format!("uri of https is fetched with curl, which cannot run: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    parse_response(&output.stdout)
+}
+
+/// the status line, headers and body of the raw response, the informational ones skipped
+fn parse_response(mut raw: &[u8]) -> Result<Fetched, String> {
+    loop {
+        let end = raw
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or("the response ends before its headers do")?;
+        let head = String::from_utf8_lossy(&raw[..end]).to_string();
+        raw = &raw[end + 4..];
+        let mut lines = head.split("\r\n");
+        let status_line = lines.next().unwrap_or_default().to_string();
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or_else(|| format!("`{}` is not a status line", status_line))?;
+        if (100..200).contains(&status) {
+            continue;
+        }
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect::<Vec<_>>();
+        let mut fetched = Fetched {
+            status_line,
+            headers,
+            body: raw.to_vec(),
+        };
+        if fetched
+            .get_header("transfer-encoding")
+            .is_some_and(|te| te.to_lowercase().contains("chunked"))
+        {
+            fetched.body = join_chunks(raw)?;
+        } else if let Some(length) = fetched
+            .get_header("content-length")
+            .and_then(|length| length.parse::<usize>().ok())
+        {
+            fetched.body.truncate(length);
+        }
+        return Ok(fetched);
+    }
+}
+
+/// the body sent in chunks
+fn join_chunks(mut raw: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    loop {
+        let end = raw
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or("the response ends before its chunks do")?;
+        let line = String::from_utf8_lossy(&raw[..end]).to_string();
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| format!("`{}` is not the size of a chunk", line))?;
+        raw = &raw[end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if raw.len() < size {
+            return Err("the response ends before its chunks do".into());
+        }
+        body.extend_from_slice(&raw[..size]);
+        raw = raw.get(size + 2..).unwrap_or_default();
+    }
+}
+
+/// the name of case after the last segment of path and the query, not taken in `dir`
+fn get_case(uri: &str, dir: &str) -> String {
+    let rest = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let segment = path
+        .split('/')
+        .skip(1)
+        .filter(|s| !s.is_empty())
+        .last()
+        .unwrap_or("index");
+    let stem = segment
+        .split('.')
+        .next()
+        .filter(|s| !s.is_empty())
+        .unwrap_or("index");
+    let mut case = if query.is_empty() {
+        stem.to_string()
+    } else {
+        format!("{}_{}", stem, query)
+    };
+    case = case
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || c == '-' => c,
+            _ => '_',
+        })
+        .take(64)
+        .collect();
+    let base = case.clone();
+    let mut n = 1;
+    while !get_case_files(dir, &case).is_empty() {
+        n += 1;
+        case = format!("{}-{}", base, n);
+    }
+    case
+}
+
+/// the body and head of case in `dir`, not the snapshot
+fn get_case_files(dir: &str, case: &str) -> Vec<std::path::PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .filter(|path| !path.to_string_lossy().ends_with(".expected.json"))
+                .filter(|path| path.file_stem().is_some_and(|stem| stem == case))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// the extension of body after its content type
+fn get_extension(content_type: &str) -> &'static str {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    match mime.as_str() {
+        "text/html" | "application/xhtml+xml" => "html",
+        "text/plain" => "txt",
+        "text/csv" => "csv",
+        "text/javascript" | "application/javascript" => "js",
+        mime if mime.ends_with("json") => "json",
+        mime if mime.ends_with("xml") => "xml",
+        _ => "body",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_response_skips_informational_responses() {
+        let raw = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nlink: </a.css>\r\n\r\nHTTP/1.1 404 Not Found\r\ncontent-type: text/html\r\nx-a: b: c\r\n\r\nmissing";
+        let fetched = parse_response(raw).unwrap();
+        assert_eq!(fetched.status_line, "HTTP/1.1 404 Not Found");
+        assert_eq!(fetched.status(), "404");
+        assert_eq!(fetched.get_header("Content-Type"), Some("text/html"));
+        assert_eq!(fetched.get_header("x-a"), Some("b: c"));
+        assert_eq!(fetched.get_header("link"), None);
+        assert_eq!(fetched.body, b"missing");
+    }
+
+    #[test]
+    fn parse_response_takes_the_body_by_content_length() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello, trailing bytes";
+        assert_eq!(parse_response(raw).unwrap().body, b"hello");
+        let raw = b"HTTP/1.1 200 OK\r\ncontent-length: nan\r\n\r\nhello";
+        assert_eq!(parse_response(raw).unwrap().body, b"hello");
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n").is_err());
+        assert!(parse_response(b"hello\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn parse_response_joins_chunked_bodies() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, chunked\r\n\r\n5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\nx-trailer: 1\r\n\r\n";
+        assert_eq!(parse_response(raw).unwrap().body, b"hello, world");
+    }
+
+    #[test]
+    fn join_chunks_reads_sizes_in_hex() {
+        let body = [&b"1a\r\n"[..], &[b'x'; 26], b"\r\n0\r\n\r\n"].concat();
+        assert_eq!(join_chunks(&body).unwrap(), vec![b'x'; 26]);
+        assert_eq!(join_chunks(b"0\r\n\r\n").unwrap(), b"");
+        assert!(join_chunks(b"5\r\nhel").is_err());
+        assert!(join_chunks(b"zz\r\nhello\r\n0\r\n\r\n").is_err());
+        assert!(join_chunks(b"5\r\nhello\r\n").is_err());
+    }
+
+    #[test]
+    fn get_case_is_named_after_the_uri() {
+        let dir = "no-such-dir";
+        assert_eq!(get_case("https://example.com", dir), "index");
+        assert_eq!(get_case("https://example.com/", dir), "index");
+        assert_eq!(get_case("http://[::1]:8080/a/b.html#top", dir), "b");
+        assert_eq!(
+            get_case("https://example.com/list/?page=2&q=a b", dir),
+            "list_page_2_q_a_b"
+        );
+        assert_eq!(get_case("https://example.com/?page=2", dir), "index_page_2");
+        assert_eq!(
+            get_case(&format!("https://example.com/{}", "x".repeat(80)), dir).len(),
+            64
+        );
+    }
+
+    #[test]
+    fn get_case_avoids_the_cases_saved() {
+        let dir = std::env::temp_dir().join(format!("dyer-fetch-cases-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_string_lossy().to_string();
+        std::fs::write(dir.join("page.html"), "").unwrap();
+        std::fs::write(dir.join("page-2.head"), "").unwrap();
+        std::fs::write(dir.join("other.expected.json"), "").unwrap();
+        let page = get_case("https://example.com/page", &dir_str);
+        let other = get_case("https://example.com/other", &dir_str);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(page, "page-3");
+        assert_eq!(other, "other");
+    }
+}
@@ -0,0 +1,51 @@
+use crate::subcommand::run::{self, MetaData};
+use crate::util;
+
+// dyer gen [--stdout | --check | --force], alias `dyer expand`
+#[derive(std::fmt::Debug)]
+pub struct SubComGen {
+    // print the main instead of writing it
+    pub stdout: bool,
+    // exit with 1 if the main on disk is out of date
+    pub check: bool,
+    // overwrite the main even if its generated portion is modified by hand
+    pub force: bool,
+}
+
+impl SubComGen {
+    pub fn execute(&self) {
+        let mut meta = MetaData::new();
+        meta.init();
+        let path = meta.get_main_path();
+        let shown = path.trim_start_matches("./");
+        if self.stdout {
+            let main_str = meta
+                .compose_main(true)
+                .unwrap_or_else(|e| util::exit_with(&e));
+            print!("{}", main_str);
+            return;
+        }
+        if self.check {
+            let old = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+                util::exit_with(&format!(
+                    "{} does not exist, run `dyer gen` to generate it",
+                    shown
+                ))
+            });
+            meta.validate().unwrap_or_else(|e| util::exit_with(&e));
+            let main_str = meta.render_main().unwrap_or_else(|e| util::exit_with(&e));
+            let expected = run::fill_regions(&main_str, &run::get_regions(&old));
+            // the user regions are kept and the log level is set when running, neither is generated
+            if run::hash_generated(&old) != run::hash_generated(&expected) {
+                util::exit_with(&format!(
+                    "{} is out of date, run `dyer gen` to regenerate it",
+                    shown
+                ));
+            }
+            println!("{} is up to date", shown);
+            return;
+        }
+        meta.make_main(self.force);
+        println!("    generate {}", shown);
+    }
+}
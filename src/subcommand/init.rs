@@ -0,0 +1,264 @@
+use crate::subcommand::new::get_vars;
+use crate::template;
+use crate::util::{self, get_file_intro, get_file_path, LogLevel};
+use std::path::Path;
+
+// dyer init [path]
+#[derive(std::fmt::Debug)]
+pub struct SubComInit {
+    pub path: String,
+    pub option: Option<LogLevel>,
+    // name of package if `Cargo.toml` is missing, the directory name as default
+    pub name: Option<String>,
+}
+
+impl SubComInit {
+    /*
+     * turn the directory, an existing cargo crate or not, into a dyer project,
+     * files already there are kept, only the missing ones are added, and the
+     * dependencies of dyer are merged into the existing `Cargo.toml`
+     */
+    pub fn execute(&self) {
+        let root = Path::new(&self.path);
+        if !root.is_dir() {
+            util::exit_with(&format!("`{}` is not a directory", self.path));
+        }
+        let manifest = root.join("Cargo.toml");
+        let existing = std::fs::read_to_string(&manifest).ok();
+        let package = existing
+            .as_deref()
+            .and_then(|m| util::get_manifest_value(m, "package", "name"));
+        let (name, crate_name) = match package {
+            Some(name) => {
+                let crate_name = existing
+                    .as_deref()
+                    .and_then(|m| util::get_manifest_value(m, "lib", "name"))
+                    .unwrap_or_else(|| name.replace('-', "_"));
+                (name, crate_name)
+            }
+            None => {
+                let name = self.name.clone().unwrap_or_else(|| {
+                    root.canonicalize()
+                        .ok()
+                        .and_then(|p| p.file_name().map(|f| f.to_string_lossy().to_string()))
+                        .unwrap_or_else(|| util::exit_with("cannot infer the name of project"))
+                });
+                let crate_name = util::validate_name(&name).unwrap_or_else(|e| {
+                    util::exit_with(&format!(
+                        "{}\nuse `--name` to specify the name of project",
+                        e
+                    ))
+                });
+                (name, crate_name)
+            }
+        };
+        // generate code compatible with the dyer already depended on
+        let dyer_version = existing
+            .as_deref()
+            .map(util::get_dyer_version)
+            .unwrap_or(util::DYER_VERSION);
+        let vars = get_vars(
+            &name,
+            &crate_name,
+            self.option.as_ref().unwrap(),
+            dyer_version,
+        );
+        // the library root if the crate already has one other than `src/actor.rs`
+        let lib_root = existing
+            .as_deref()
+            .and_then(|m| util::get_manifest_value(m, "lib", "path"))
+            .or_else(|| {
+                if root.join("src/lib.rs").exists() {
+                    Some("src/lib.rs".to_string())
+                } else {
+                    None
+                }
+            })
+            .filter(|path| path.trim_start_matches("./") != "src/actor.rs");
+
+        std::fs::create_dir_all(root.join("data/tasks")).unwrap();
+        std::fs::create_dir_all(root.join("src/bin")).unwrap();
+        let indexs = [
+            "readme",
+            "entity",
+            "parser",
+            "affix",
+            "actor",
+            "middleware",
+            "pipeline",
+            "config",
+        ];
+        for index in indexs.iter() {
+            let path = get_file_path(index, self.path.clone());
+            if Path::new(&path).exists() {
+                println!("    keep  {}", path);
+                continue;
+            }
+            let mut buf = template::render_str(get_file_intro(index), &vars);
+            if index == &"actor" && lib_root.is_some() {
+                buf = as_submodule(&buf);
+            }
+            std::fs::write(&path, buf).unwrap();
+            println!("    add   {}", path);
+        }
+        if let Some(lib_root) = lib_root.as_ref() {
+            let path = root.join(lib_root);
+            let buf = std::fs::read_to_string(&path).unwrap_or_default();
+            let mut lines = Vec::new();
+            for module in [
+                "actor",
+                "affix",
+                "entity",
+                "middleware",
+                "parser",
+                "pipeline",
+            ]
+            .iter()
+            {
+                if !buf.contains(&format!("mod {};", module)) {
+                    lines.push(format!("pub mod {};", module));
+                }
+            }
+            if !buf.contains("pub use actor::*;") {
+                lines.push("pub use actor::*;".to_string());
+            }
+            if !lines.is_empty() {
+                std::fs::write(&path, declare_modules(&buf, &lines)).unwrap();
+                println!("    edit  {}: declare the modules of dyer", path.display());
+            }
+        }
+
+        let cargo = template::render_str(get_file_intro("cargo"), &vars);
+        match existing {
+            Some(existing) => {
+                let sections: &[&str] = if lib_root.is_some() {
+                    &["bin"]
+                } else {
+                    &["lib", "bin"]
+                };
+                let (merged, added) = util::merge_manifest(&existing, &cargo, sections);
+                if !added.is_empty() {
+                    std::fs::write(&manifest, merged).unwrap();
+                }
+                for item in added.iter() {
+                    println!("    edit  {}: add {}", manifest.display(), item);
+                }
+            }
+            None => {
+                std::fs::write(&manifest, format!("{}\n", cargo.trim_end())).unwrap();
+                println!("    add   {}", manifest.display());
+            }
+        }
+        if root.join("src/main.rs").exists() {
+            println!(
+                "note: src/main.rs is not the binary any more, the main generated by dyer lives in src/bin/"
+            );
+        }
+        println!("project {} is initialized successfully!", name);
+    }
+}
+
+// the library root with `declarations` put after its inner attributes and doc comments, which
+// are not permitted after items
+fn declare_modules(buf: &str, declarations: &[String]) -> String {
+    let mut end = 0;
+    let mut offset = 0;
+    // inside a `#![...]` or `/*! ... */` spanning lines
+    let mut depth = 0;
+    let mut in_doc = false;
+    for line in buf.split_inclusive('\n') {
+        offset += line.len();
+        let text = line.trim();
+        if in_doc {
+            in_doc = !text.contains("*/");
+        } else if depth > 0 {
+            depth += brackets(text);
+        } else if let Some(doc) = text.strip_prefix("/*!") {
+            in_doc = !doc.contains("*/");
+        } else if text.starts_with("#![") {
+            depth = brackets(text);
+        } else if !text.starts_with("//!") {
+            if text.is_empty() || text.starts_with("//") {
+                continue;
+            }
+            break;
+        }
+        end = offset;
+    }
+    let (inner, rest) = buf.split_at(end);
+    let inner = match inner.is_empty() {
+        true => String::new(),
+        false => format!("{}\n\n", inner.trim_end()),
+    };
+    format!("{}{}\n{}", inner, declarations.join("\n"), rest)
+}
+
+// the opened brackets of the line less the closed ones
+fn brackets(text: &str) -> i32 {
+    text.chars()
+        .map(|c| match c {
+            '[' => 1,
+            ']' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+// the actor is not the library root, so the modules are declared by the root
+fn as_submodule(actor: &str) -> String {
+    actor
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("pub mod "))
+        .map(|line| match line.trim().strip_prefix("use ") {
+            Some(rest) if !rest.starts_with("dyer") && !rest.starts_with("crate") => {
+                format!("use crate::{}", rest)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+        .trim_start()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declarations() -> Vec<String> {
+        vec![
+            "pub mod actor;".to_string(),
+            "pub use actor::*;".to_string(),
+        ]
+    }
+
+    #[test]
+    fn declare_modules_keeps_inner_attributes_and_docs_in_front() {
+        let buf = "//! the crate\n//! of items\n#![deny(missing_docs)]\n#![cfg_attr(\n    docsrs,\n    feature(doc_cfg)\n)]\n\n// helpers\nmod util;\n";
+        assert_eq!(
+            declare_modules(buf, &declarations()),
+            "//! the crate\n//! of items\n#![deny(missing_docs)]\n#![cfg_attr(\n    docsrs,\n    feature(doc_cfg)\n)]\n\npub mod actor;\npub use actor::*;\n\n// helpers\nmod util;\n"
+        );
+        let buf = "/*!\n * the crate\n */\n\n#![allow(dead_code)]\nmod util;";
+        assert_eq!(
+            declare_modules(buf, &declarations()),
+            "/*!\n * the crate\n */\n\n#![allow(dead_code)]\n\npub mod actor;\npub use actor::*;\nmod util;"
+        );
+    }
+
+    #[test]
+    fn declare_modules_puts_them_first_without_inner_attributes() {
+        assert_eq!(
+            declare_modules("// helpers\nmod util;\n", &declarations()),
+            "pub mod actor;\npub use actor::*;\n// helpers\nmod util;\n"
+        );
+        assert_eq!(
+            declare_modules("", &declarations()),
+            "pub mod actor;\npub use actor::*;\n"
+        );
+        assert_eq!(
+            declare_modules("#[macro_use]\nextern crate log;\n", &declarations()),
+            "pub mod actor;\npub use actor::*;\n#[macro_use]\nextern crate log;\n"
+        );
+    }
+}
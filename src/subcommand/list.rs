@@ -0,0 +1,415 @@
+use crate::subcommand::run::{self, Item, MetaData};
+use crate::util::json_str;
+
+// dyer list [--json]
+#[derive(std::fmt::Debug)]
+pub struct SubComList {
+    // print the inventory as json instead of text
+    pub json: bool,
+}
+
+/// a variant of the entities enum
+#[derive(std::fmt::Debug)]
+struct Variant {
+    name: String,
+    line: usize,
+}
+
+/// something the generator ignores or chokes on
+#[derive(std::fmt::Debug)]
+pub(crate) struct Warning {
+    pub path: String,
+    pub line: usize,
+    pub message: String,
+    // the main cannot be generated at all
+    pub fatal: bool,
+}
+
+impl SubComList {
+    pub fn execute(&self) {
+        let mut meta = MetaData::new();
+        // the items are listed as far as they are found, with the problem as `dyer doctor` shows it
+        let problem = meta.try_init().err();
+        if problem.is_some() {
+            meta.has_affix =
+                std::path::Path::new(&format!("{}src/affix.rs", meta.base_dir)).exists();
+        }
+        let mut items = Vec::new();
+        let mut variants = Vec::new();
+        for path in meta.get_inputs().iter().filter(|p| p.ends_with(".rs")) {
+            let buf =
+                std::fs::read_to_string(format!("{}{}", meta.base_dir, path)).unwrap_or_default();
+            for item in run::get_items(path, &buf) {
+                if item.module == "entity" && item.typ == "enum" {
+                    variants.push(get_variants(&buf, item.line));
+                } else {
+                    variants.push(Vec::new());
+                }
+                items.push(item);
+            }
+        }
+        let warnings = get_warnings(&items, meta.has_affix);
+        if self.json {
+            print_json(&items, &variants, &warnings);
+        } else {
+            print_text(&items, &variants, &warnings);
+        }
+        if let Some(problem) = problem {
+            eprintln!("error: {}", problem);
+            eprintln!("    fix: correct it so that the main can be generated, `dyer doctor` checks the rest");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// the variants of the enum whose attribute is at `line`, with their lines
+fn get_variants(buf: &str, line: usize) -> Vec<Variant> {
+    let head = regex::Regex::new(r"\benum\s+\w+").unwrap();
+    // the attributes and doc comments of the enum aside, it starts at the line declaring it
+    let start = buf.lines().enumerate().skip(line - 1).find(|(_, text)| {
+        let code = text.split("//").next().unwrap_or_default();
+        !code.trim_start().starts_with('#') && head.is_match(code)
+    });
+    let (mut line, offset) = match start {
+        Some((i, _)) => (
+            i + 1,
+            buf.split_inclusive('\n')
+                .take(i)
+                .map(|l| l.len())
+                .sum::<usize>(),
+        ),
+        None => return Vec::new(),
+    };
+    let mut variants = Vec::new();
+    let mut depth = 0;
+    // a variant starts with the first identifier after `{` or `,` inside the braces of the enum,
+    // its attributes are inside brackets and its doc comments are skipped
+    let mut expecting = false;
+    let mut chars = buf[offset..].chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '"' => {
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        '\n' => line += 1,
+                        '"' if !escaped => break,
+                        _ => {}
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+            }
+            '{' | '(' | '[' => {
+                depth += 1;
+                if depth == 1 {
+                    expecting = true;
+                }
+            }
+            '}' | ')' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            ',' if depth == 1 => expecting = true,
+            c if depth == 1 && expecting && (c.is_alphabetic() || c == '_') => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                variants.push(Variant { name, line });
+                expecting = false;
+            }
+            _ => {}
+        }
+    }
+    variants
+}
+
+/// the hooks the generated main leaves out or fails with
+pub(crate) fn get_warnings(items: &[Item], has_affix: bool) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let mut warn = |fatal: bool, message: String| {
+            warnings.push(Warning {
+                path: item.path.clone(),
+                line: item.line,
+                message,
+                fatal,
+            })
+        };
+        let expected = format!("src/{}.rs", item.module);
+        if item.path != expected {
+            warn(
+                false,
+                format!(
+                    "`#[dyer::{}]` is only discovered in {}, `{}` is not used as one",
+                    item.module, expected, item.ident
+                ),
+            );
+            continue;
+        }
+        if let Err(e) = run::check_hook(&item.module, item.key.as_deref(), &item.ident) {
+            warn(true, e);
+            continue;
+        }
+        let key = match item.key.as_deref() {
+            Some(key) if ["middleware", "pipeline"].contains(&item.module.as_str()) => key,
+            _ => continue,
+        };
+        if key == "handle_affix" && !has_affix {
+            warn(
+                false,
+                format!(
+                    "`{}` is ignored, the project generates no affix",
+                    item.ident
+                ),
+            );
+        }
+        // the last one bound to a hook wins
+        if let Some(other) = items[i + 1..]
+            .iter()
+            .find(|o| o.path == item.path && o.key == item.key)
+        {
+            warn(
+                false,
+                format!(
+                    "`{}` is ignored, `{}` at line {} is bound to `{}` as well",
+                    item.ident, other.ident, other.line, key
+                ),
+            );
+        }
+    }
+    warnings
+}
+
+fn print_text(items: &[Item], variants: &[Vec<Variant>], warnings: &[Warning]) {
+    let sections = [
+        ("actor", "actor"),
+        ("entity", "entities"),
+        ("parser", "parsers"),
+        ("middleware", "middleware"),
+        ("pipeline", "pipeline"),
+        ("affix", "affix"),
+    ];
+    for (module, title) in sections.iter() {
+        let found = items
+            .iter()
+            .zip(variants.iter())
+            .filter(|(item, _)| item.module == *module)
+            .collect::<Vec<_>>();
+        if found.is_empty() {
+            continue;
+        }
+        println!("{}:", title);
+        for (item, variants) in found {
+            let name = match item.key.as_deref() {
+                Some(key) if ["middleware", "pipeline"].contains(module) => {
+                    format!("{:<16}{}", key, item.ident)
+                }
+                _ => item.ident.clone(),
+            };
+            println!("    {:<40}{}:{}", name, item.path, item.line);
+            for variant in variants {
+                println!("        {:<36}{}:{}", variant.name, item.path, variant.line);
+            }
+        }
+    }
+    if warnings.is_empty() {
+        return;
+    }
+    println!("warnings:");
+    for warning in warnings {
+        println!("    {}:{}: {}", warning.path, warning.line, warning.message);
+    }
+}
+
+fn print_json(items: &[Item], variants: &[Vec<Variant>], warnings: &[Warning]) {
+    let location =
+        |path: &str, line: usize| format!("\"file\": {}, \"line\": {}", json_str(path), line);
+    let items = items
+        .iter()
+        .zip(variants.iter())
+        .map(|(item, variants)| {
+            let variants = variants
+                .iter()
+                .map(|v| {
+                    format!(
+                        "{{\"name\": {}, {}}}",
+                        json_str(&v.name),
+                        location(&item.path, v.line)
+                    )
+                })
+                .collect::<Vec<String>>();
+            format!(
+                "    {{\"kind\": {}, \"hook\": {}, \"name\": {}, {}, \"variants\": [{}]}}",
+                json_str(&item.module),
+                item.key.as_deref().map_or("null".to_string(), json_str),
+                json_str(&item.ident),
+                location(&item.path, item.line),
+                variants.join(", ")
+            )
+        })
+        .collect::<Vec<String>>();
+    let warnings = warnings
+        .iter()
+        .map(|w| {
+            format!(
+                "    {{{}, \"message\": {}}}",
+                location(&w.path, w.line),
+                json_str(&w.message)
+            )
+        })
+        .collect::<Vec<String>>();
+    let array = |elements: Vec<String>| match elements.is_empty() {
+        true => "[]".to_string(),
+        false => format!("[\n{}\n  ]", elements.join(",\n")),
+    };
+    println!(
+        "{{\n  \"items\": {},\n  \"warnings\": {}\n}}",
+        array(items),
+        array(warnings)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(module: &str, key: Option<&str>, ident: &str, line: usize) -> Item {
+        Item {
+            module: module.into(),
+            key: key.map(|key| key.into()),
+            typ: "fn".into(),
+            ident: ident.into(),
+            path: format!("src/{}.rs", module),
+            line,
+        }
+    }
+
+    fn names(variants: &[Variant]) -> Vec<(&str, usize)> {
+        variants.iter().map(|v| (v.name.as_str(), v.line)).collect()
+    }
+
+    #[test]
+    fn get_variants_finds_tuple_and_struct_like_variants() {
+        let buf = r#"use serde::Serialize;
+
+#[dyer::entity(entities)]
+#[derive(Serialize, Debug)]
+/// the enum of all entities
+pub enum Entities {
+    /// an Item found on a page
+    Item(Item),
+    Page {
+        /// Title of the page
+        Title: String,
+        url: String,
+    },
+    Pair(
+        // Left one
+        Left,
+        Right,
+    ),
+    Unit, Other
+}
+
+pub enum Unrelated {
+    Never,
+}
+"#;
+        assert_eq!(
+            names(&get_variants(buf, 3)),
+            vec![
+                ("Item", 8),
+                ("Page", 9),
+                ("Pair", 14),
+                ("Unit", 19),
+                ("Other", 19)
+            ]
+        );
+    }
+
+    #[test]
+    fn get_variants_skips_the_attributes_of_variants() {
+        let buf = r#"#[dyer::entity(entities)]
+#[doc = "the enum of all entities"]
+pub enum Entities {
+    #[serde(rename = "https://example.com/{item}")]
+    Item(Item),
+    #[serde(
+        rename = "Page",
+        skip_serializing_if = "Option::is_none"
+    )]
+    Page(Option<Page>),
+    #[allow(dead_code)] /* Unit */ Unit,
+}"#;
+        assert_eq!(
+            names(&get_variants(buf, 1)),
+            vec![("Item", 5), ("Page", 10), ("Unit", 11)]
+        );
+        assert!(get_variants("#[dyer::entity(entities)]\npub struct Item {}\n", 1).is_empty());
+    }
+
+    #[test]
+    fn get_warnings_reports_the_ignored_and_fatal_hooks() {
+        let mut items = vec![
+            item("middleware", Some("handle_req"), "first", 2),
+            item("middleware", Some("handle_affix"), "affix", 5),
+            item("middleware", Some("handle_req"), "second", 8),
+            item("pipeline", Some("initialiser"), "open", 3),
+            item("pipeline", Some("disposer"), "close", 6),
+        ];
+        items.push(Item {
+            path: "src/parser.rs".into(),
+            ..item("middleware", Some("handle_res"), "misplaced", 9)
+        });
+        let warnings = get_warnings(&items, false);
+        let found = warnings
+            .iter()
+            .map(|w| (w.path.as_str(), w.line, w.fatal))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("src/middleware.rs", 2, false),
+                ("src/middleware.rs", 5, false),
+                ("src/pipeline.rs", 3, true),
+                ("src/parser.rs", 9, false),
+            ]
+        );
+        assert_eq!(
+            warnings[0].message,
+            "`first` is ignored, `second` at line 8 is bound to `handle_req` as well"
+        );
+        assert_eq!(
+            warnings[1].message,
+            "`affix` is ignored, the project generates no affix"
+        );
+        assert!(warnings[2].message.contains("did you mean `initializer`?"));
+        assert_eq!(
+            warnings[3].message,
+            "`#[dyer::middleware]` is only discovered in src/middleware.rs, `misplaced` is not used as one"
+        );
+        // with an affix, `handle_affix` is used
+        assert_eq!(get_warnings(&items, true).len(), 3);
+    }
+}
@@ -1,28 +1,58 @@
 pub mod new;
+pub mod init;
 pub mod run;
 pub mod check;
 pub mod fix;
 pub mod build;
 pub mod clean;
 pub mod test;
+pub mod upgrade;
+pub mod gen;
+pub mod watch;
+pub mod list;
+pub mod doctor;
+pub mod test_parser;
+pub mod serve;
+pub mod fetch;
+pub mod shell;
 
 pub use check::SubComCheck;
 pub use fix::SubComFix;
 pub use new::SubComNew;
+pub use init::SubComInit;
 pub use run::SubComRun;
 pub use build::SubComBuild;
 pub use clean::SubComClean;
 pub use test::SubComTest;
+pub use upgrade::SubComUpgrade;
+pub use gen::SubComGen;
+pub use watch::SubComWatch;
+pub use list::SubComList;
+pub use doctor::SubComDoctor;
+pub use test_parser::SubComTestParser;
+pub use serve::SubComServe;
+pub use fetch::SubComFetch;
+pub use shell::SubComShell;
 
 #[derive(std::fmt::Debug)]
 pub enum SubCommand {
     SubComNew(SubComNew),
+    SubComInit(SubComInit),
     SubComRun(SubComRun),
     SubComFix(SubComFix),
     SubComCheck(SubComCheck),
     SubComBuild(SubComBuild),
     SubComClean(SubComClean),
     SubComTest(SubComTest),
+    SubComUpgrade(SubComUpgrade),
+    SubComGen(SubComGen),
+    SubComWatch(SubComWatch),
+    SubComList(SubComList),
+    SubComDoctor(SubComDoctor),
+    SubComTestParser(SubComTestParser),
+    SubComServe(SubComServe),
+    SubComFetch(SubComFetch),
+    SubComShell(SubComShell),
     Null,
 }
 impl SubCommand {
@@ -31,6 +61,9 @@ impl SubCommand {
             SubCommand::SubComNew(command) => {
                 command.execute();
             }
+            SubCommand::SubComInit(command) => {
+                command.execute();
+            }
             SubCommand::SubComRun(command) => {
                 command.execute();
             }
@@ -49,6 +82,33 @@ impl SubCommand {
             SubCommand::SubComTest(command) => {
                 command.execute();
             }
+            SubCommand::SubComUpgrade(command) => {
+                command.execute();
+            }
+            SubCommand::SubComGen(command) => {
+                command.execute();
+            }
+            SubCommand::SubComWatch(command) => {
+                command.execute();
+            }
+            SubCommand::SubComList(command) => {
+                command.execute();
+            }
+            SubCommand::SubComDoctor(command) => {
+                command.execute();
+            }
+            SubCommand::SubComTestParser(command) => {
+                command.execute();
+            }
+            SubCommand::SubComServe(command) => {
+                command.execute();
+            }
+            SubCommand::SubComFetch(command) => {
+                command.execute();
+            }
+            SubCommand::SubComShell(command) => {
+                command.execute();
+            }
             _ => {}
         }
     }
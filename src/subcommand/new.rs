@@ -1,11 +1,37 @@
-use crate::util::{get_file_intro, get_file_path, LogLevel};
+use crate::feature;
+use crate::kind;
+use crate::template::{self, Template};
+use crate::util::{self, get_file_intro, get_file_path, LogLevel};
+use std::collections::HashMap;
 use std::io::Write;
 
-// dyer new <+name+>
+// dyer new <+path+> [--name <+name+>]
 #[derive(std::fmt::Debug)]
 pub struct SubComNew {
+    // directory of the project
+    pub path: String,
+    // name of package, the last component of `path` as default
     pub name: String,
     pub option: Option<LogLevel>,
+    // directory or name of an installed template
+    pub template: Option<String>,
+    // built-in starter kind such as `sitemap`
+    pub kind: Option<String>,
+    // additional placeholders given by `--var key=value`
+    pub vars: Vec<(String, String)>,
+    pub list_templates: bool,
+    // overwrite the files of an existing directory
+    pub force: bool,
+    // version control system, `git` or `none`, git when available as default
+    pub vcs: Option<String>,
+    // version of dyer to depend on, `util::DYER_VERSION` as default
+    pub dyer_version: Option<String>,
+    // cargo features of dyer given by `--features a,b`
+    pub features: Option<String>,
+    // select the features of dyer interactively
+    pub interactive: bool,
+    // generate no `src/affix.rs`
+    pub no_affix: bool,
 }
 
 impl SubComNew {
@@ -22,17 +48,101 @@ impl SubComNew {
      *    |___src/pipeline.rs
      */
     pub fn execute(&self) {
+        if self.list_templates {
+            return self.list();
+        }
         let name = &self.name;
-        let level = match self.option.as_ref().unwrap() {
-            LogLevel::Error => "Error",
-            LogLevel::Warn => "Warn",
-            LogLevel::Info => "Info",
-            LogLevel::Debug => "Debug",
-            LogLevel::Trace => "Trace",
+        let crate_name = util::validate_name(name).unwrap_or_else(|e| util::exit_with(&e));
+        let path = &self.path;
+        let target = std::path::Path::new(path);
+        let occupied = target.is_file()
+            || std::fs::read_dir(target)
+                .map(|mut entries| entries.next().is_some())
+                .unwrap_or(false);
+        if occupied && !self.force {
+            util::exit_with(&format!(
+                "destination `{}` already exists and is not empty, use `--force` to overwrite it, or `dyer init` inside it to add the missing files only",
+                path
+            ));
+        }
+        let dyer_version = self.dyer_version.as_deref().unwrap_or(util::DYER_VERSION);
+        let supported =
+            util::resolve_dyer_version(dyer_version).unwrap_or_else(|e| util::exit_with(&e));
+        // the code of features is appended to the built-in files, which a template does not have
+        if self.template.is_some() && (self.interactive || self.features.is_some()) {
+            util::exit_with(
+                "`--features` and `-i` cannot be used with `--template`, put the features of dyer into the Cargo.toml of template",
+            );
+        }
+        let features = match (self.interactive, self.features.as_deref()) {
+            (true, Some(_)) => util::exit_with("`-i` and `--features` cannot be used together"),
+            (true, None) => feature::prompt_features(supported),
+            (false, Some(features)) => feature::parse_features(supported, features),
+            (false, None) => Ok(Vec::new()),
+        }
+        .unwrap_or_else(|e| util::exit_with(&e));
+        let mut vars = get_vars(
+            name,
+            &crate_name,
+            self.option.as_ref().unwrap(),
+            dyer_version,
+        );
+        let kind = self.kind.as_deref().unwrap_or_default();
+        if !kind.is_empty() {
+            if !kind::KINDS.iter().any(|(k, _)| k == &kind) {
+                let kinds = kind::KINDS.iter().map(|(k, _)| *k).collect::<Vec<&str>>();
+                util::exit_with(&format!(
+                    "unknown kind `{}`, available kinds: {}",
+                    kind,
+                    kinds.join(", ")
+                ));
+            }
+            if self.template.is_some() {
+                util::exit_with("`--kind` and `--template` cannot be used together");
+            }
+        }
+        if !kind.is_empty() {
+            for (key, value) in kind::get_kind_vars(kind).iter() {
+                vars.insert(key.to_string(), value.to_string());
+            }
+        }
+        let dyer_features = features
+            .iter()
+            .map(|f| format!("\"{}\"", f))
+            .collect::<Vec<String>>();
+        vars.insert("dyer_features".to_string(), dyer_features.join(", "));
+        if self.no_affix {
+            // templates may leave out their affix with `when.src/affix.rs: !no_affix`
+            vars.insert("no_affix".to_string(), "true".to_string());
+        }
+        vars.extend(self.vars.iter().cloned());
+        let git = match self.vcs.as_deref() {
+            None => is_git_available(),
+            Some("none") => false,
+            Some("git") if is_git_available() => true,
+            Some("git") => util::exit_with("`--vcs git` is given but git cannot be found"),
+            Some(vcs) => util::exit_with(&format!(
+                "unknown version control system `{}`, use `git` or `none`",
+                vcs
+            )),
         };
-        std::fs::create_dir_all(format!("{}/data/tasks/", name)).unwrap();
-        std::fs::create_dir_all(format!("{}/src", name)).unwrap();
-        std::fs::create_dir_all(format!("{}/src/bin/", name)).unwrap();
+        let template = self
+            .template
+            .as_ref()
+            .map(|path| Template::find(path).unwrap_or_else(|e| util::exit_with(&e)));
+        std::fs::create_dir_all(format!("{}/data/tasks/", path)).unwrap();
+        std::fs::create_dir_all(format!("{}/src", path)).unwrap();
+        std::fs::create_dir_all(format!("{}/src/bin/", path)).unwrap();
+        if let Some(template) = template {
+            if let Err(e) = template.render(path, &vars) {
+                util::exit_with(&format!("failed to render template: {}", e));
+            }
+            if git {
+                self.init_git(&vars);
+            }
+            println!("project {} is created successfully!", name);
+            return;
+        }
         let indexs = [
             "cargo",
             "readme",
@@ -45,17 +155,177 @@ impl SubComNew {
             "config",
         ];
         indexs.iter().for_each(|index| {
-            let path = get_file_path(index, name.clone());
-            let buf = get_file_intro(index)
-                .replace("<+name+>", name)
-                .replace("<+log_level+>", level);
+            if index == &"affix" && self.no_affix {
+                return;
+            }
+            let path = get_file_path(index, path.clone());
+            let intro = kind::get_kind_intro(kind, index).unwrap_or_else(|| get_file_intro(index));
+            let mut buf = template::render_str(intro, &vars);
+            if index == &"cargo" && !kind.is_empty() {
+                buf = format!("{}\n{}\n", buf.trim_end(), kind::KIND_DEPENDENCIES);
+            }
+            if index == &"actor" && self.no_affix {
+                buf = util::remove_affix(&buf);
+            }
+            for feature in feature::expand_features(supported, &features) {
+                if let Some(intro) = feature::get_feature_intro(feature, index) {
+                    buf = format!("{}\n{}", buf.trim_end(), template::render_str(intro, &vars));
+                }
+            }
             let mut file = std::fs::OpenOptions::new()
                 .create(true)
                 .write(true)
+                .truncate(true)
                 .open(path)
                 .unwrap();
-            file.write(buf.as_bytes()).unwrap();
+            file.write_all(buf.as_bytes()).unwrap();
         });
+        for (file, intro) in kind::get_kind_files(kind).into_iter() {
+            let path = std::path::Path::new(path).join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, template::render_str(intro, &vars)).unwrap();
+        }
+        if git {
+            self.init_git(&vars);
+        }
         println!("project {} is created successfully!", name);
     }
+
+    /// initialize a git repository like cargo, unless it is inside one and `--vcs git` is not given
+    fn init_git(&self, vars: &HashMap<String, String>) {
+        let inside = std::process::Command::new("git")
+            .args(["rev-parse", "--is-inside-work-tree"])
+            .current_dir(&self.path)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false);
+        if inside && self.vcs.is_none() {
+            return;
+        }
+        let status = std::process::Command::new("git")
+            .arg("init")
+            .arg("--quiet")
+            .current_dir(&self.path)
+            .status();
+        if !status.map(|status| status.success()).unwrap_or(false) {
+            util::exit_with(&format!(
+                "failed to initialize git repository in {}",
+                self.path
+            ));
+        }
+        // a template may bring its own
+        let path = get_file_path("gitignore", self.path.clone());
+        if !std::path::Path::new(&path).exists() {
+            let buf = template::render_str(get_file_intro("gitignore"), vars);
+            std::fs::write(path, buf).unwrap();
+        }
+    }
+
+    fn list(&self) {
+        println!("built-in kinds, used by `dyer new <name> --kind <kind>`:");
+        for (name, description) in kind::KINDS.iter() {
+            println!("    {:<20}{}", name, description);
+        }
+        let templates = template::list_templates();
+        let dir = template::get_templates_dir()
+            .map(|d| d.display().to_string())
+            .unwrap_or_default();
+        if templates.is_empty() {
+            println!("\nno template installed in {}", dir);
+            return;
+        }
+        println!("\ntemplates installed in {}:", dir);
+        for (name, description) in templates.iter() {
+            println!("    {:<20}{}", name, description);
+        }
+    }
+}
+
+fn is_git_available() -> bool {
+    std::process::Command::new("git")
+        .arg("--version")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// the placeholders of built-in files, `dyer_version` is one of the supported versions or a release of it
+pub(crate) fn get_vars(
+    name: &str,
+    crate_name: &str,
+    level: &LogLevel,
+    dyer_version: &str,
+) -> HashMap<String, String> {
+    let supported = util::resolve_dyer_version(dyer_version).unwrap();
+    let level = match level {
+        LogLevel::Error => "Error",
+        LogLevel::Warn => "Warn",
+        LogLevel::Info => "Info",
+        LogLevel::Debug => "Debug",
+        LogLevel::Trace => "Trace",
+    };
+    let mut vars = HashMap::new();
+    vars.insert("name".to_string(), name.to_string());
+    vars.insert("crate_name".to_string(), crate_name.to_string());
+    vars.insert("log_level".to_string(), level.to_string());
+    vars.insert("author".to_string(), util::get_author());
+    vars.insert("dyer_version".to_string(), dyer_version.to_string());
+    vars.insert("dyer_features".to_string(), String::new());
+    vars.insert(
+        "tokio_dependency".to_string(),
+        util::get_version_rule(supported, "tokio").to_string(),
+    );
+    vars.insert(
+        "actor_marker".to_string(),
+        util::get_version_rule(supported, "marker").replace("<+marker+>", "MyActor"),
+    );
+    vars.insert(
+        "res_bytes".to_string(),
+        util::get_version_rule(supported, "res_bytes").to_string(),
+    );
+    for (key, value) in kind::get_kind_vars("").iter() {
+        vars.insert(key.to_string(), value.to_string());
+    }
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(version: &str, kind: &str, index: &str) -> String {
+        let vars = get_vars("demo", "demo", &LogLevel::Info, version);
+        let intro = kind::get_kind_intro(kind, index).unwrap_or_else(|| get_file_intro(index));
+        template::render_str(intro, &vars)
+    }
+
+    #[test]
+    fn intros_of_dyer_3_2_mark_tasks_with_a_string_and_read_the_body_by_chunk() {
+        let actor = render("3.2", "", "actor");
+        assert!(actor.contains(r#".body(Body::empty(), "MyActor".into())"#));
+        for (kind, _) in kind::KINDS.iter() {
+            let actor = render("3.2", kind, "actor");
+            let parser = render("3.2", kind, "parser");
+            assert!(!actor.contains(r#""MyActor")"#), "{}", kind);
+            assert!(!parser.contains(r#""MyActor")"#), "{}", kind);
+            assert!(!parser.contains(".bytes()"), "{}", kind);
+            assert!(!parser.contains("<+"), "{}", kind);
+        }
+        let parser = render("3.2", "sitemap", "parser");
+        assert!(parser.contains(r#".body(Body::empty(), "MyActor".into())"#));
+        assert!(parser.contains("&res.body().iter().flat_map(|chunk| chunk.iter().copied())"));
+    }
+
+    #[test]
+    fn intros_of_dyer_3_3_mark_tasks_with_a_str_and_read_the_body_at_once() {
+        let actor = render("3.3", "", "actor");
+        assert!(actor.contains(r#".body(Body::empty(), "MyActor")"#));
+        let parser = render("3.3.1", "json-api", "parser");
+        assert!(parser.contains(r#".body(Body::empty(), "MyActor")"#));
+        assert!(parser.contains("serde_json::from_slice(&res.body().bytes())"));
+    }
 }
@@ -1,351 +1,1808 @@
-#![allow(dead_code)]
-use crate::util;
-use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
-use std::io::{BufRead, BufReader, Read, Write};
-use std::str::FromStr;
-
-#[derive(std::fmt::Debug)]
-pub struct SubComRun {
-    pub options: Vec<String>,
-}
-
-#[derive(Debug)]
-pub(crate) struct MetaData {
-    modules: HashMap<String, Module>,
-    pkgs: Vec<String>,
-    ctype: String,
-    base_dir: String,
-    pub package_name: String,
-}
-
-impl MetaData {
-    pub fn new() -> Self {
-        MetaData {
-            modules: HashMap::new(),
-            pkgs: vec!["std".to_string()],
-            ctype: String::new(),
-            base_dir: "./".into(),
-            package_name: String::new(),
-        }
-    }
-
-    pub(crate) fn hash(&self) -> (bool, u64) {
-        let paths = [
-            "Cargo.toml",
-            "middleware",
-            "pipeline",
-            "parser",
-            "affix",
-            "entity",
-            "actor",
-        ];
-        let mut h = DefaultHasher::new();
-        for path in paths.iter() {
-            let path_ = if path == &"Cargo.toml" {
-                format!("{}Cargo.toml", &self.base_dir)
-            } else {
-                format!("{}src/{}.rs", &self.base_dir, path)
-            };
-            let mut file = std::fs::File::open(&path_).unwrap();
-            let mut buf = String::new();
-            file.read_to_string(&mut buf).unwrap();
-            buf.hash(&mut h);
-        }
-        let hash = h.finish();
-        let mut f = std::fs::OpenOptions::new()
-            .create(true)
-            .read(true)
-            .write(true)
-            .open(format!("{}.dyertrace", &self.base_dir))
-            .unwrap();
-        let mut bf = String::new();
-        f.read_to_string(&mut bf).unwrap();
-        let old = bf.trim().parse::<u64>().unwrap_or(0);
-        //println!("old: {}, new: {}", old, hash);
-        if old != hash {
-            let mut ff = std::fs::OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(format!("{}.dyertrace", &self.base_dir))
-                .unwrap();
-            let s = format!("{}", hash);
-            ff.write(&s.as_bytes()).unwrap();
-        }
-
-        (old == hash, hash)
-    }
-
-    pub(crate) fn init(&mut self) {
-        self.get_pkg();
-        let paths = [
-            "middleware",
-            "pipeline",
-            "parser",
-            "entity",
-            "affix",
-            "actor",
-        ];
-        let raw_pat = r"(?sm)^\s*#\[(?P<module>((dyer::)?middleware)|((dyer::)?pipeline)|((dyer::)?affix)|((dyer::)?entity)|((dyer::)?actor)|((dyer::)?parser))(\(\s*(?P<key>\w+)\s*\))?\].*?(?P<typ>(fn)|(struct)|(enum))\s*(?P<ident>\w+)((?u-sm).*?\->.*?Option<(?P<ctyp>.*?)>)?";
-        let ctype_pat = r"(?sm)^\s*#\[\s*pipeline\s*\(\s*initializer\s*\)\s*\].*?fn\s*(?P<ident>\w+).*?Option<(?P<ctyp>.*?)>";
-
-        for i in 0..paths.len() {
-            let pat = regex::Regex::from_str(&raw_pat).unwrap();
-            let path = format!("{}src/{}.rs", self.base_dir, paths[i]);
-            //println!("path: {}", path);
-            let mut file = std::fs::File::open(&path).expect(&format!("path: {} Not Found", path));
-            let mut handles = HashMap::new();
-            let mut buf = String::new();
-            file.read_to_string(&mut buf).unwrap();
-            for cap in pat.captures_iter(&buf) {
-                //println!("path: {}, cap {:?}", paths[i], cap);
-                let mut module = cap.name("module").unwrap().as_str();
-                if module.starts_with("dyer") {
-                    module = module.strip_prefix("dyer::").unwrap();
-                }
-                let value = cap.name("ident").unwrap().as_str().to_string();
-                let key = if ["actor", "parser", "affix"].contains(&module) {
-                    value.clone()
-                } else {
-                    cap.name("key").unwrap().as_str().to_string()
-                };
-                if paths[i] == "pipeline" && &key == "initializer" {
-                    let ctype = match cap.name("ctyp") {
-                        Some(c) => c.as_str().to_string(),
-                        None => {
-                            let ctype_pat = regex::Regex::from_str(&ctype_pat).unwrap();
-                            if let Some(c) = ctype_pat.captures(&buf) {
-                                c.name("ctyp").unwrap().as_str().to_string()
-                            } else {
-                                panic!("failed to extract return type of `initializer`");
-                            }
-                        }
-                    };
-                    //println!(" {:?}", ctype);
-                    self.ctype = ctype;
-                }
-                handles.insert(key, value);
-            }
-            let module = Module { path, handles };
-            self.modules.insert(paths[i].to_string(), module);
-        }
-    }
-
-    pub fn get_pkg(&mut self) {
-        let files = std::fs::read_dir(&self.base_dir)
-            .unwrap()
-            .map(|p| p.unwrap().path().to_str().unwrap().into())
-            .collect::<Vec<String>>();
-        if !files
-            .iter()
-            .fold(false, |acc, file| acc || file.contains(&"Cargo.toml"))
-        {
-            panic!("current directory must contain `Cargo.toml` file");
-        }
-        let path = format!("{}/Cargo.toml", self.base_dir);
-        let mut pkgs = Vec::new();
-        let file = std::fs::File::open(path).unwrap();
-        let reader = BufReader::new(file);
-        let pat = regex::Regex::new(r"^\s*([\w|-]+)\s*=\s*").unwrap();
-        let pat1 = regex::Regex::new(r"^\s*name\s*=.*?(?P<pkg_name>[\w|-]+)").unwrap();
-        let pat2 = regex::Regex::new(r"^\s*\[dependencies\]").unwrap();
-        let pat3 = regex::Regex::new(r"^\s*\[.*?\]").unwrap();
-        let mut in_content = false;
-        for line in reader.lines() {
-            let text = line.unwrap();
-            if pat2.is_match(&text) {
-                in_content = true;
-            } else if !pat2.is_match(&text) && pat3.is_match(&text) {
-                in_content = false;
-            }
-            if in_content {
-                if let Some(t) = pat.captures(&text) {
-                    let pkg = t.get(1).unwrap().as_str().trim().replace("-", "_");
-                    pkgs.push(pkg)
-                }
-            }
-            if pat1.is_match(&text) {
-                let name = pat1
-                    .captures(&text)
-                    .unwrap()
-                    .name("pkg_name")
-                    .unwrap()
-                    .as_str()
-                    .replace("-", "_");
-                self.package_name = name.into();
-            }
-        }
-        self.pkgs.extend(pkgs);
-        //println!("packages: {:?}", self.pkgs);
-    }
-
-    fn complete_path(&self) -> bool {
-        let pieces = self
-            .ctype
-            .split("::")
-            .map(|piece| piece.trim())
-            .collect::<Vec<&str>>();
-        let subpath = pieces[0].to_string();
-        if !self.pkgs.contains(&subpath) {
-            return false;
-        }
-        true
-    }
-
-    pub fn get_pkg_list(&self) -> String {
-        let list = self
-            .pkgs
-            .iter()
-            .filter(|&ele| ele != "std")
-            .map(|md| format!("extern crate {};", md))
-            .collect::<Vec<String>>();
-        list.join("\n")
-    }
-
-    pub fn make_main(&self) {
-        let entity = self.modules.get("entity").expect("entity cannot be none");
-        let entities = entity.handles.get("entities").unwrap();
-        let actor = self
-            .modules
-            .get("actor")
-            .unwrap()
-            .handles
-            .values()
-            .collect::<Vec<&String>>()[0];
-        let get_middleware_list = self.modules.get("middleware").unwrap().get_list();
-        let get_pipeline_list = self.modules.get("pipeline").unwrap().get_list();
-        let get_pipeline_map = self.modules.get("pipeline").unwrap().get_map();
-        let get_middleware_map = self.modules.get("middleware").unwrap().get_map();
-        let ctype = if self.complete_path() {
-            &self.ctype
-        } else {
-            "_"
-        };
-        let get_pkg_list = self.get_pkg_list();
-        let package_name = &self.package_name;
-
-        let main_str = r#"<+get_pkg_list+>
-extern crate <+package_name+>; 
-
-use dyer::*;
-use <+package_name+>::entity::*;
-use <+package_name+>::<+actor+>;
-use <+package_name+>::middleware::*;
-use <+package_name+>::pipeline::*;
-
-#[tokio::main]
-async fn main() {
-    simple_logger::SimpleLogger::new()
-        .with_level(log::LevelFilter::Info)
-        .init()
-        .unwrap();
-    let middleware = MiddleWare::<<+entities+>>::builder()
-        <+get_middleware_map+>
-        // Identifier of the middleware
-        .build("<+marker+>")
-    ;
-    let pipeline = PipeLine::<<+entities+>, <+ctype+>>::builder()
-        <+get_pipeline_map+>
-        // Identifier of the pipeline
-        .build("<+marker+>")
-    ;
-    let mut actor = <+actor+>::new().await;
-    let mut app = dyer::App::<<+entities+>>::new();
-    app.run(&mut actor, &middleware, &pipeline).await.unwrap();
-}
-        "#;
-        let main_str = main_str.replace("<+package_name+>", &package_name);
-        let main_str = main_str.replace("<+entities+>", &entities);
-        let main_str = main_str.replace("<+actor+>", &actor);
-        let main_str = main_str.replace("<+marker+>", &actor);
-        let main_str = main_str.replace("<+get_pkg_list+>", &get_pkg_list);
-        let main_str = main_str.replace("<+get_middleware_list+>", &get_middleware_list);
-        let main_str = main_str.replace("<+get_middleware_map+>", &get_middleware_map);
-        let main_str = main_str.replace("<+get_pipeline_list+>", &get_pipeline_list);
-        let main_str = main_str.replace("<+get_pipeline_map+>", &get_pipeline_map);
-        let main_str = main_str.replace("<+ctype+>", ctype);
-        let main_path = format!("{}src/bin/{}.rs", self.base_dir, package_name);
-        let mut main_file = std::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(main_path)
-            .unwrap();
-        main_file.write(&main_str.as_bytes()).unwrap();
-    }
-}
-
-#[derive(Debug)]
-struct Module {
-    path: String,
-    handles: HashMap<String, String>,
-}
-
-impl Module {
-    pub fn get_list(&self) -> String {
-        self.handles
-            .values()
-            .map(|val| val.as_str())
-            .collect::<Vec<&str>>()
-            .join(", ")
-    }
-
-    pub fn get_map(&self) -> String {
-        let mut ms = Vec::new();
-        for (key, val) in self.handles.iter() {
-            let output = match key.as_str() {
-                "initializer" => format!(".initializer(&{})", val),
-                "disposer" => format!(".disposer(&{})", val),
-                "process_entity" => format!(".entity(&{})", val),
-                "process_yerr" => format!(".yerr(&{})", val),
-                "handle_task" => format!(".task(&{})", val),
-                "handle_affix" => format!(".affix(&{})", val),
-                "handle_entity" => format!(".entity(&{})", val),
-                "handle_req" => format!(".req(&{})", val),
-                "handle_res" => format!(".res(&{})", val),
-                "handle_err" => format!(".err(&{})", val),
-                "handle_yerr" => format!(".yerr(&{})", val),
-                _ => unreachable!("Invalid, attribute"),
-            };
-            ms.push(output);
-        }
-        ms.join(",\n        ")
-    }
-}
-
-impl SubComRun {
-    pub fn execute(&self) {
-        let paths = std::fs::read_dir("./src/bin")
-            .unwrap()
-            .map(|p| p.unwrap().path().to_str().unwrap().into())
-            .collect::<Vec<String>>();
-        //println!("files in \"./\" {:?}", paths);
-        let pkg_name = util::get_package_name();
-        if !paths
-            .iter()
-            .fold(false, |acc, x| acc || x.contains(&pkg_name))
-        {
-            let mut meta = MetaData::new();
-            meta.init();
-            //println!("{:?}", meta);
-            meta.make_main();
-        }
-        let options = self
-            .options
-            .iter()
-            .map(|op| op.as_str())
-            .filter(|op| {
-                if ["--off", "--error", "--warn", "--info", "--debug", "--trace"].contains(&op) {
-                    util::change_log_level(op);
-                    return false;
-                }
-                true
-            })
-            .collect::<Vec<&str>>();
-        let mut args = vec!["run"];
-        args.extend(options);
-        util::run_command("cargo", args);
-    }
-}
+#![allow(dead_code)]
+use crate::template;
+use crate::util;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::str::FromStr;
+
+/// the file recording the inputs of the generated main
+pub(crate) const FINGERPRINT: &str = ".dyer-fingerprint";
+/// bumped when the format of fingerprint or the way to generate the main changes
+const FINGERPRINT_VERSION: u32 = 7;
+/// seconds to wait for shutdown unless `grace-period` of `[package.metadata.dyer]` is given
+const DEFAULT_GRACE_PERIOD: &str = "30";
+/// the main template of project overriding [`MAIN_TEMPLATE`]
+pub(crate) const MAIN_TEMPLATE_PATH: &str = "templates/main.rs.tpl";
+
+/// the modules of library scanned for annotated items, `src/<module>.rs` each
+pub(crate) const MODULES: [&str; 6] = [
+    "middleware",
+    "pipeline",
+    "parser",
+    "entity",
+    "affix",
+    "actor",
+];
+/// hooks of `MiddleWare`, the argument of `#[dyer::middleware(..)]`
+pub(crate) const MIDDLEWARE_HOOKS: [&str; 7] = [
+    "handle_task",
+    "handle_affix",
+    "handle_entity",
+    "handle_req",
+    "handle_res",
+    "handle_err",
+    "handle_yerr",
+];
+/// hooks of `PipeLine`, the argument of `#[dyer::pipeline(..)]`
+pub(crate) const PIPELINE_HOOKS: [&str; 4] =
+    ["initializer", "disposer", "process_entity", "process_yerr"];
+/// an item annotated with an attribute of dyer, the return type inside `Option` of initializer as well
+const ITEM_PATTERN: &str = r"(?sm)^\s*#\[(?P<module>((dyer::)?middleware)|((dyer::)?pipeline)|((dyer::)?affix)|((dyer::)?entity)|((dyer::)?actor)|((dyer::)?parser))(\(\s*(?P<key>\w+)\s*\))?\].*?(?P<typ>(fn)|(struct)|(enum))\s*(?P<ident>\w+)((?u-sm).*?\->.*?Option<(?P<ctyp>.*?)>)?";
+
+/// whether `key` of `#[dyer::<module>(key)]` on `ident` is a hook, the valid ones listed if not
+pub(crate) fn check_hook(module: &str, key: Option<&str>, ident: &str) -> Result<(), String> {
+    let hooks: &[&str] = match module {
+        "middleware" => &MIDDLEWARE_HOOKS,
+        "pipeline" => &PIPELINE_HOOKS,
+        _ => return Ok(()),
+    };
+    match key {
+        Some(key) if hooks.contains(&key) => Ok(()),
+        Some(key) => {
+            let suggestion = util::get_closest(key, hooks)
+                .map(|hook| format!(", did you mean `{}`?", hook))
+                .unwrap_or_else(|| ",".into());
+            Err(format!(
+                "unknown hook `{}` of `#[dyer::{}]` on `{}`{} valid hooks: {}",
+                key,
+                module,
+                ident,
+                suggestion,
+                hooks.join(", ")
+            ))
+        }
+        None => Err(format!(
+            "`#[dyer::{}]` on `{}` names no hook, eg. `#[dyer::{}({})]`, valid hooks: {}",
+            module,
+            ident,
+            module,
+            hooks[0],
+            hooks.join(", ")
+        )),
+    }
+}
+
+/// the signature of a function as written
+#[derive(std::fmt::Debug)]
+pub(crate) struct Signature {
+    pub is_async: bool,
+    // types of the parameters
+    pub params: Vec<String>,
+    // `()` if it returns nothing
+    pub ret: String,
+    // line of the function, starting from 1
+    pub line: usize,
+}
+
+/// the signature of `fn ident` in source `buf`
+pub(crate) fn parse_signature(buf: &str, ident: &str) -> Option<Signature> {
+    let pat = regex::Regex::new(&format!(
+        r"(?P<async>\basync\s+)?(unsafe\s+)?(extern\s+\S+\s+)?\bfn\s+{}\b",
+        regex::escape(ident)
+    ))
+    .unwrap();
+    let cap = pat.captures(buf)?;
+    let head = cap.get(0).unwrap();
+    let rest = &buf[head.end()..];
+    // the generic parameters are skipped, the parameters are inside the first parentheses
+    let open = rest.find('(')?;
+    let mut depth = 0;
+    let mut close = None;
+    for (i, c) in rest[open..].char_indices() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '>' if !rest[open..open + i].ends_with('-') => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            close = Some(open + i);
+            break;
+        }
+    }
+    let close = close?;
+    let params = split_top_level(&rest[open + 1..close])
+        .into_iter()
+        .filter_map(|param| {
+            // the pattern before the first single `:`
+            let bytes = param.as_bytes();
+            let colon = (0..bytes.len()).find(|&i| {
+                bytes[i] == b':'
+                    && bytes.get(i + 1) != Some(&b':')
+                    && (i == 0 || bytes[i - 1] != b':')
+            })?;
+            Some(param[colon + 1..].trim().to_string())
+        })
+        .collect();
+    let tail = &rest[close + 1..];
+    let end = tail.find(['{', ';']).unwrap_or(tail.len());
+    // the where clause may start on a line of its own
+    let clause = regex::Regex::new(r"\swhere\b").unwrap();
+    let tail = clause.split(&tail[..end]).next().unwrap_or_default().trim();
+    let ret = match tail.strip_prefix("->") {
+        Some(ret) => ret.trim().to_string(),
+        None => "()".to_string(),
+    };
+    Some(Signature {
+        is_async: cap.name("async").is_some(),
+        params,
+        ret,
+        line: buf[..head.start()].matches('\n').count() + 1,
+    })
+}
+
+/// split `s` by the commas outside of any brackets, the empty pieces left out
+fn split_top_level(s: &str) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut depth = 0;
+    let mut piece = String::new();
+    let mut last = ' ';
+    for c in s.chars() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '>' if last != '-' => depth -= 1,
+            ',' if depth == 0 => {
+                pieces.push(std::mem::take(&mut piece));
+                last = c;
+                continue;
+            }
+            _ => {}
+        }
+        piece.push(c);
+        last = c;
+    }
+    pieces.push(piece);
+    pieces
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// `E` of a type in [`util::get_hook_signature`] replaced with the entities
+fn replace_entities(typ: &str, entities: &str) -> String {
+    let pat = regex::Regex::new(r"\bE\b").unwrap();
+    pat.replace_all(typ, entities).to_string()
+}
+
+/// a type compared regardless of lifetimes, paths and spaces
+fn normalize_type(typ: &str) -> String {
+    let lifetime = regex::Regex::new(r"'\w+\s*").unwrap();
+    let path = regex::Regex::new(r"\b(\w+::)+").unwrap();
+    let typ = lifetime.replace_all(typ, "");
+    let typ = path.replace_all(&typ, "");
+    typ.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// an item of the library annotated with an attribute of dyer
+#[derive(std::fmt::Debug)]
+pub(crate) struct Item {
+    // `middleware`, `pipeline`, `parser`, `entity`, `affix` or `actor`
+    pub module: String,
+    // the argument of attribute, eg. `handle_req` of `#[dyer::middleware(handle_req)]`
+    pub key: Option<String>,
+    // `fn`, `struct` or `enum`
+    pub typ: String,
+    pub ident: String,
+    pub path: String,
+    // line of the attribute, starting from 1
+    pub line: usize,
+}
+
+/// the annotated items of source `buf` at `path`, in the order they appear
+pub(crate) fn get_items(path: &str, buf: &str) -> Vec<Item> {
+    let pat = regex::Regex::new(ITEM_PATTERN).unwrap();
+    pat.captures_iter(buf)
+        .map(|cap| {
+            let module = cap.name("module").unwrap();
+            Item {
+                module: module.as_str().trim_start_matches("dyer::").to_string(),
+                key: cap.name("key").map(|k| k.as_str().to_string()),
+                typ: cap["typ"].to_string(),
+                ident: cap["ident"].to_string(),
+                path: path.to_string(),
+                line: buf[..module.start()].matches('\n').count() + 1,
+            }
+        })
+        .collect()
+}
+
+/// the built-in template of the generated main
+const MAIN_TEMPLATE: &str = r#"<+get_pkg_list+>
+extern crate <+package_name+>; 
+
+use dyer::*;
+use <+package_name+>::entity::*;
+use <+package_name+>::<+actor+>;
+use <+package_name+>::middleware::*;
+use <+package_name+>::pipeline::*;
+<+region_imports+>
+
+<+runtime+>
+async fn main() {
+    simple_logger::SimpleLogger::new()
+        .with_level(<+log_level+>)
+        .init()
+        .unwrap();
+    let middleware = MiddleWare::<<+entities+>>::builder()
+        <+get_middleware_map+>
+        // Identifier of the middleware
+        .build(<+marker_expr+>)
+    ;
+    let pipeline = PipeLine::<<+entities+>, <+ctype+>>::builder()
+        <+get_pipeline_map+>
+        // Identifier of the pipeline
+        .build(<+marker_expr+>)
+    ;
+    let mut actor = <+actor+>::new().await;
+    let mut app = dyer::App::<<+entities+>>::new();
+    <+handle_signals_call+>
+    <+cassette_call+>
+    <+region_before-run+>
+    app.run(&mut actor, &middleware, &pipeline).await.unwrap();
+    <+region_after-run+>
+}
+<+handle_signals+>
+<+cassette+>
+<+wrapped_hooks+>
+"#;
+
+/// shut down in order on SIGTERM as on SIGINT, exit at once on another signal or the grace period passed
+const HANDLE_SIGNALS: &str = r#"
+/* dyer shuts down in order on Ctrl+c, flushing entities through pipeline and storing pending tasks
+ * in `data/`, SIGTERM is turned into SIGINT to do the same. Another signal, or the grace period
+ * passed, exits at once.
+ */
+fn handle_signals(grace: std::time::Duration) {
+    fn escalate(received: usize, grace: std::time::Duration) {
+        if received > 1 {
+            log::warn!("Receive Another Signal, Exiting At Once");
+            std::process::exit(130);
+        }
+        std::thread::spawn(move || {
+            std::thread::sleep(grace);
+            log::warn!("Grace Period Passed, Exiting At Once");
+            std::process::exit(130);
+        });
+    }
+    tokio::spawn(async move {
+        let mut received = 0;
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            extern "C" {
+                fn raise(sig: i32) -> i32;
+            }
+            let mut term = signal(SignalKind::terminate()).unwrap();
+            let mut int = signal(SignalKind::interrupt()).unwrap();
+            // the SIGINT raised for SIGTERM is not another signal
+            let mut raised = 0;
+            loop {
+                tokio::select! {
+                    _ = term.recv() => {
+                        raised += 1;
+                        unsafe { raise(2) };
+                    }
+                    _ = int.recv() => {
+                        if raised > 0 {
+                            raised -= 1;
+                            continue;
+                        }
+                    }
+                }
+                received += 1;
+                escalate(received, grace);
+            }
+        }
+        #[cfg(not(unix))]
+        loop {
+            tokio::signal::ctrl_c().await.unwrap();
+            received += 1;
+            escalate(received, grace);
+        }
+    });
+}"#;
+
+/// the responses saved by `dyer run --record` and served by `dyer run --replay`
+const CASSETTE: &str = r#"
+/* `dyer run --record <dir>` saves every response into the cassette in <dir>, the body as `<n>.body`,
+ * the status and headers as `<n>.head`, the body of its request if any as `<n>.req`, and a line
+ * `<n>\t<method>\t<digest of request body>\t<uri>` in `index`. `dyer run --replay <dir>` serves them
+ * from a local stand-in instead of the network, every request is redirected to it before being sent,
+ * the responses of the same method, uri and request body in the order recorded.
+ */
+mod cassette {
+    // the half of recording or replaying is not used
+    #![allow(dead_code)]
+    use dyer::*;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::Mutex;
+
+    const DIR: &str = <+cassette_dir+>;
+    /// the headers describing the body as sent, not as saved
+    const FRAMING: &[&str] = &[<+cassette_framing+>];
+
+    /// the number of responses recorded
+    static RECORDED: Mutex<usize> = Mutex::new(0);
+    /// the id, method and body of the requests sent and not yet answered
+    static SENT: Mutex<Vec<(u64, String, Vec<u8>)>> = Mutex::new(Vec::new());
+    /// the address of the stand-in, and the responses of each request with the next one to serve
+    static TAPE: Mutex<Option<(String, HashMap<String, (Vec<usize>, usize)>)>> = Mutex::new(None);
+
+    fn bytes(body: &Body) -> Vec<u8> {
+        body.iter()
+            .flat_map(|chunk| chunk.iter().copied())
+            .collect()
+    }
+
+    /// the fnv-1a hash of the body, stable across builds, `-` if empty
+    fn digest(body: &[u8]) -> String {
+        if body.is_empty() {
+            return "-".to_string();
+        }
+        let hash = body.iter().fold(0xcbf29ce484222325u64, |hash, b| {
+            (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    /// the request a response is recorded for and looked up by
+    fn key(method: &str, uri: &str, body: &[u8]) -> String {
+        format!("{}\t{}\t{}", method, digest(body), uri)
+    }
+
+    /// discard the cassette in the directory and record a new one
+    pub fn start_recording() {
+        std::fs::create_dir_all(DIR).unwrap();
+        for entry in std::fs::read_dir(DIR).unwrap().filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if [".body", ".head", ".req"].iter().any(|ext| name.ends_with(ext)) {
+                std::fs::remove_file(entry.path()).unwrap();
+            }
+        }
+        std::fs::write(format!("{}/index", DIR), "").unwrap();
+        log::info!("Recording Responses Into {}", DIR);
+    }
+
+    /// keep the method and body of the requests about to be sent, saved with their responses
+    pub fn note(reqs: &[Request]) {
+        let mut sent = SENT.lock().unwrap();
+        for req in reqs.iter() {
+            sent.push((
+                req.metar.info.id,
+                req.inner.method.to_string(),
+                bytes(&req.body),
+            ));
+        }
+    }
+
+    /// the method and body of the request answered by the response of `id`
+    fn take_sent(id: u64) -> (String, Vec<u8>) {
+        let mut sent = SENT.lock().unwrap();
+        match sent.iter().position(|(sent_id, _, _)| *sent_id == id) {
+            Some(pos) => {
+                let (_, method, body) = sent.remove(pos);
+                (method, body)
+            }
+            None => ("GET".to_string(), Vec::new()),
+        }
+    }
+
+    pub fn record(res: &Response) {
+        let (method, req_body) = take_sent(res.metas.info.id);
+        let mut recorded = RECORDED.lock().unwrap();
+        let n = *recorded;
+        std::fs::write(format!("{}/{}.body", DIR, n), bytes(&res.body)).unwrap();
+        let mut head = format!("{}\n", res.inner.status.as_u16());
+        for (name, value) in res.inner.headers.iter() {
+            head.push_str(&format!(
+                "{}: {}\n",
+                name,
+                String::from_utf8_lossy(value.as_bytes())
+            ));
+        }
+        std::fs::write(format!("{}/{}.head", DIR, n), head).unwrap();
+        if !req_body.is_empty() {
+            std::fs::write(format!("{}/{}.req", DIR, n), &req_body).unwrap();
+        }
+        let uri = res.metas.info.from.to_string();
+        let line = format!("{}\t{}\n", n, key(&method, &uri, &req_body));
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(format!("{}/index", DIR))
+            .and_then(|mut index| index.write_all(line.as_bytes()))
+            .unwrap();
+        *recorded += 1;
+    }
+
+    /// record the responses received, and forget the requests failed without one
+    pub fn record_results(items: &[Result<Response, MetaResponse>]) {
+        for item in items.iter() {
+            match item {
+                Ok(res) => record(res),
+                Err(meta) => drop(take_sent(meta.info.id)),
+            }
+        }
+    }
+
+    /// serve the cassette in the directory on a local port
+    pub fn start_replaying() {
+        let index = std::fs::read_to_string(format!("{}/index", DIR))
+            .unwrap_or_else(|_| panic!("no cassette is recorded in {}", DIR));
+        let mut keys: HashMap<String, (Vec<usize>, usize)> = HashMap::new();
+        for line in index.lines() {
+            let (n, key) = match line.split_once('\t') {
+                Some((n, key)) => (n, key),
+                None => continue,
+            };
+            if let Ok(n) = n.parse::<usize>() {
+                keys.entry(key.to_string()).or_default().0.push(n);
+            }
+        }
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|s| s.ok()) {
+                std::thread::spawn(move || serve(stream));
+            }
+        });
+        log::info!("Replaying Responses From {} On {}", DIR, addr);
+        *TAPE.lock().unwrap() = Some((addr, keys));
+    }
+
+    /// the status and headers saved in `<n>.head`, those framing the body left out
+    fn read_head(n: usize) -> Option<(String, Vec<(String, String)>)> {
+        let head = std::fs::read_to_string(format!("{}/{}.head", DIR, n)).ok()?;
+        let mut lines = head.lines();
+        let status = lines.next()?.trim().to_string();
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .filter(|(name, _)| !FRAMING.contains(&name.to_lowercase().as_str()))
+            .collect();
+        Some((status, headers))
+    }
+
+    /// answer the requests of a connection with the responses numbered by their paths
+    fn serve(stream: std::net::TcpStream) {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let path = line.split_whitespace().nth(1).unwrap_or("/").to_string();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap_or(0) == 0 {
+                    return;
+                }
+                match header.trim().split_once(':') {
+                    Some((key, value)) if key.eq_ignore_ascii_case("content-length") => {
+                        length = value.trim().parse().unwrap_or(0);
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            let mut body = vec![0; length];
+            if reader.read_exact(&mut body).is_err() {
+                return;
+            }
+            let found = path
+                .trim_start_matches('/')
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| read_head(n).map(|head| (n, head)));
+            let (status, headers, body) = match found {
+                Some((n, (status, headers))) => (
+                    status,
+                    headers,
+                    std::fs::read(format!("{}/{}.body", DIR, n)).unwrap_or_default(),
+                ),
+                None => ("404".to_string(), Vec::new(), Vec::new()),
+            };
+            let mut head = format!(
+                "HTTP/1.1 {} Replayed\r\ncontent-length: {}\r\n",
+                status,
+                body.len()
+            );
+            for (name, value) in headers.iter() {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            head.push_str("\r\n");
+            let mut stream = reader.get_ref();
+            if stream
+                .write_all(head.as_bytes())
+                .and_then(|_| stream.write_all(&body))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    /// the uri on the stand-in of the next response recorded for the request
+    fn redirect(method: &str, uri: &str, body: &[u8]) -> String {
+        let mut tape = TAPE.lock().unwrap();
+        let (addr, keys) = tape.as_mut().unwrap();
+        let path = match keys.get_mut(&key(method, uri, body)) {
+            Some((numbers, next)) => {
+                let n = numbers[(*next).min(numbers.len() - 1)];
+                *next += 1;
+                n.to_string()
+            }
+            None => {
+                log::warn!("Not In The Cassette: {} {}", method, uri);
+                "missing".to_string()
+            }
+        };
+        format!("http://{}/{}", addr, path)
+    }
+
+    /// redirect the requests not yet redirected, the uri they are made for is kept in their info
+    pub fn replay(reqs: &mut Vec<Request>) {
+        let addr = TAPE.lock().unwrap().as_ref().unwrap().0.clone();
+        for req in reqs.iter_mut() {
+            if req.inner.uri.authority().map(|a| a.as_str()) == Some(addr.as_str()) {
+                continue;
+            }
+            let uri = redirect(
+                req.inner.method.as_str(),
+                &req.info().from.to_string(),
+                &bytes(&req.body),
+            );
+            req.inner.uri = uri.parse().unwrap();
+        }
+    }
+}"#;
+
+/// where the requests are sent with `serve` of `[package.metadata.dyer]`
+const SERVE_LOCALLY: &str = r#"
+/// send the requests to the server of `dyer serve` at `origin`, the uri parsers see is kept
+fn serve_locally(reqs: &mut Vec<Request>, origin: &str) {
+    for req in reqs.iter_mut() {
+        let path = req.inner.uri.path_and_query().map_or("/", |p| p.as_str()).to_string();
+        req.inner.uri = format!("{}{}", origin, path).parse().unwrap();
+    }
+}"#;
+
+/// the headers of `[package.metadata.dyer.headers]` put on the requests
+const SET_HEADERS: &str = r#"
+/// set the headers the requests do not have, those set by the actor or the hooks are kept
+fn set_headers(reqs: &mut Vec<Request>, headers: &[(&'static str, &str)]) {
+    for req in reqs.iter_mut() {
+        for (name, value) in headers.iter() {
+            if !req.inner.headers.contains_key(*name) {
+                req.inner.headers.insert(*name, value.parse().unwrap());
+            }
+        }
+    }
+}"#;
+
+/// the parts of the generated main kept as they are when it is regenerated
+const REGIONS: [&str; 3] = ["imports", "before-run", "after-run"];
+
+fn region_begin(region: &str) -> String {
+    format!(
+        "// ---- user region `{}`, kept when the main is regenerated ----",
+        region
+    )
+}
+
+fn region_end(region: &str) -> String {
+    format!("// ---- end of user region `{}` ----", region)
+}
+
+/// the content of user regions in the main
+pub(crate) fn get_regions(buf: &str) -> HashMap<String, String> {
+    let mut regions = HashMap::new();
+    let mut current: Option<(&str, Vec<&str>)> = None;
+    for line in buf.split('\n') {
+        match current.take() {
+            Some((region, lines)) if line.trim() == region_end(region) => {
+                regions.insert(region.to_string(), lines.join("\n"));
+            }
+            Some((region, mut lines)) => {
+                lines.push(line);
+                current = Some((region, lines));
+            }
+            None => {
+                current = REGIONS
+                    .iter()
+                    .find(|region| line.trim() == region_begin(region))
+                    .map(|region| (*region, Vec::new()));
+            }
+        }
+    }
+    regions
+}
+
+/// put the content of user regions into the main
+pub(crate) fn fill_regions(buf: &str, regions: &HashMap<String, String>) -> String {
+    let mut lines = Vec::new();
+    let mut skipping = None;
+    for line in buf.split('\n') {
+        if let Some(region) = skipping {
+            if line.trim() != region_end(region) {
+                continue;
+            }
+            skipping = None;
+        } else if let Some(region) = REGIONS.iter().find(|r| line.trim() == region_begin(r)) {
+            lines.push(line.to_string());
+            match regions.get(*region) {
+                Some(content) if !content.is_empty() => lines.push(content.clone()),
+                _ => {}
+            }
+            skipping = Some(*region);
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    lines.join("\n")
+}
+
+/// the hash of main without user regions, nor the log level set when running
+pub(crate) fn hash_generated(buf: &str) -> String {
+    let level = regex::Regex::new(r"log::LevelFilter::\w+").unwrap();
+    let generated = fill_regions(buf, &HashMap::new());
+    let generated = level.replace_all(&generated, "log::LevelFilter::_");
+    format!("{:016x}", util::stable_hash(generated.as_bytes()))
+}
+
+#[derive(std::fmt::Debug)]
+pub struct SubComRun {
+    pub options: Vec<String>,
+    // `--record <dir>` or `--replay <dir>`
+    pub cassette: Option<Cassette>,
+}
+
+/// the responses of `dyer run` saved into or served from a directory
+#[derive(std::fmt::Debug, Clone)]
+pub enum Cassette {
+    Record(String),
+    Replay(String),
+}
+
+#[derive(Debug)]
+pub(crate) struct MetaData {
+    modules: HashMap<String, Module>,
+    pkgs: Vec<String>,
+    ctype: String,
+    pub(crate) base_dir: String,
+    // the crate identifier of library
+    pub package_name: String,
+    // the file stem of the generated main in `src/bin/`
+    pub bin_name: String,
+    // the supported version of dyer the project depends on
+    pub dyer_version: &'static str,
+    // whether the project has `src/affix.rs` and its actor generates an affix
+    pub has_affix: bool,
+    // whether tokio has feature `signal` to handle signals with
+    pub has_signal: bool,
+    // whether dyer has feature `compression` and decodes the bodies it receives
+    pub decompresses: bool,
+    // seconds to wait for shutdown before exiting at once
+    pub grace_period: String,
+    // the main records or replays the responses
+    pub cassette: Option<Cassette>,
+    // the origin of `dyer serve` the requests are sent to
+    pub serve: Option<String>,
+    // the headers set on the requests without them
+    pub headers: Vec<(String, String)>,
+}
+
+impl MetaData {
+    pub fn new() -> Self {
+        MetaData {
+            modules: HashMap::new(),
+            pkgs: vec!["std".to_string()],
+            ctype: String::new(),
+            base_dir: "./".into(),
+            package_name: String::new(),
+            bin_name: String::new(),
+            dyer_version: util::DYER_VERSION,
+            has_affix: false,
+            has_signal: false,
+            decompresses: false,
+            grace_period: String::new(),
+            cassette: None,
+            serve: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// the inputs of the generated main: the stable hash of every file it is made from, along with
+    /// the version of dyer-cli and the settings of generation
+    pub(crate) fn fingerprint(&self) -> String {
+        let mut lines = vec![
+            "# generated by dyer-cli, the main is regenerated when anything below changes"
+                .to_string(),
+            format!("version: {}", FINGERPRINT_VERSION),
+            format!("cli: {}", env!("CARGO_PKG_VERSION")),
+            format!("setting.dyer_version: {}", self.dyer_version),
+            format!("setting.package_name: {}", self.package_name),
+            format!("setting.bin_name: {}", self.bin_name),
+            format!("setting.has_affix: {}", self.has_affix),
+            format!("setting.has_signal: {}", self.has_signal),
+            format!("setting.decompresses: {}", self.decompresses),
+            format!("setting.grace_period: {}", self.grace_period),
+            format!(
+                "setting.cassette: {}",
+                match self.cassette.as_ref() {
+                    Some(Cassette::Record(dir)) => format!("record {}", dir),
+                    Some(Cassette::Replay(dir)) => format!("replay {}", dir),
+                    None => "none".into(),
+                }
+            ),
+            format!("setting.serve: {}", self.serve.as_deref().unwrap_or("none")),
+            format!(
+                "setting.headers: {}",
+                self.headers
+                    .iter()
+                    .map(|(name, value)| format!("{}={:?}", name, value))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+        ];
+        for path in self.get_inputs() {
+            let buf = std::fs::read(format!("{}{}", self.base_dir, path)).unwrap_or_default();
+            lines.push(format!("file.{}: {:016x}", path, util::stable_hash(&buf)));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// `Cargo.toml`, `dyer.cfg` and the sources of library, relative to the base directory
+    pub(crate) fn get_inputs(&self) -> Vec<String> {
+        let mut inputs = vec!["Cargo.toml".to_string()];
+        for path in ["dyer.cfg", MAIN_TEMPLATE_PATH].iter() {
+            if std::path::Path::new(&format!("{}{}", self.base_dir, path)).exists() {
+                inputs.push(path.to_string());
+            }
+        }
+        let mut dirs = vec!["src".to_string()];
+        while let Some(dir) = dirs.pop() {
+            let entries = match std::fs::read_dir(format!("{}{}", self.base_dir, dir)) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = format!("{}/{}", dir, entry.file_name().to_string_lossy());
+                if entry.path().is_dir() {
+                    // the generated main is not an input of itself
+                    if path != "src/bin" {
+                        dirs.push(path);
+                    }
+                } else if path.ends_with(".rs") {
+                    inputs.push(path);
+                }
+            }
+        }
+        inputs[1..].sort();
+        inputs
+    }
+
+    /// whether the generated main exists and is made from the current inputs
+    pub(crate) fn is_fresh(&self) -> bool {
+        if !std::path::Path::new(&self.get_main_path()).exists() {
+            return false;
+        }
+        match std::fs::read_to_string(format!("{}{}", self.base_dir, FINGERPRINT)) {
+            Ok(old) => {
+                let inputs = old
+                    .lines()
+                    .filter(|line| !line.starts_with("main: "))
+                    .map(|line| format!("{}\n", line))
+                    .collect::<String>();
+                inputs == self.fingerprint()
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// the hash of the generated portion of main recorded when it is generated
+    fn get_recorded_main(&self) -> Option<String> {
+        let old = std::fs::read_to_string(format!("{}{}", self.base_dir, FINGERPRINT)).ok()?;
+        old.lines()
+            .find_map(|line| line.strip_prefix("main: "))
+            .map(|hash| hash.to_string())
+    }
+
+    /// record the inputs and the generated portion once the main is generated
+    fn save_fingerprint(&self, main_str: &str) {
+        let path = format!("{}{}", self.base_dir, FINGERPRINT);
+        let buf = format!("{}main: {}\n", self.fingerprint(), hash_generated(main_str));
+        std::fs::write(path, buf).unwrap();
+        // superseded by the fingerprint
+        let _ = std::fs::remove_file(format!("{}.dyertrace", self.base_dir));
+    }
+
+    /// discover the package and the annotated items, quit with the problem if they cannot be
+    pub(crate) fn init(&mut self) {
+        self.try_init().unwrap_or_else(|e| util::exit_with(&e));
+    }
+
+    /// discover the package and the annotated items, `Err` on a project in the middle of an edit
+    pub(crate) fn try_init(&mut self) -> Result<(), String> {
+        self.get_pkg()?;
+        let paths = MODULES;
+        let raw_pat = ITEM_PATTERN;
+        let ctype_pat = r"(?sm)^\s*#\[\s*pipeline\s*\(\s*initializer\s*\)\s*\].*?fn\s*(?P<ident>\w+).*?Option<(?P<ctyp>.*?)>";
+
+        for name in paths.iter() {
+            let pat = regex::Regex::from_str(raw_pat).unwrap();
+            let path = format!("{}src/{}.rs", self.base_dir, name);
+            //println!("path: {}", path);
+            if name == &"affix" && !std::path::Path::new(&path).exists() {
+                continue;
+            }
+            let mut handles = HashMap::new();
+            let buf = std::fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read {}: {}", path.trim_start_matches("./"), e))?;
+            for cap in pat.captures_iter(&buf) {
+                //println!("path: {}, cap {:?}", name, cap);
+                let mut module = cap.name("module").unwrap().as_str();
+                if module.starts_with("dyer") {
+                    module = module.strip_prefix("dyer::").unwrap();
+                }
+                // an item is discovered only in the file of its module
+                if module != *name {
+                    continue;
+                }
+                let value = cap.name("ident").unwrap().as_str().to_string();
+                let key = if ["actor", "parser", "affix"].contains(&module) {
+                    value.clone()
+                } else {
+                    // reported by `check_hooks`
+                    match cap.name("key") {
+                        Some(key) => key.as_str().to_string(),
+                        None => continue,
+                    }
+                };
+                if name == &"pipeline" && &key == "initializer" {
+                    let ctype = match cap.name("ctyp") {
+                        Some(c) => c.as_str().to_string(),
+                        None => {
+                            let ctype_pat = regex::Regex::from_str(ctype_pat).unwrap();
+                            match ctype_pat.captures(&buf) {
+                                Some(c) => c.name("ctyp").unwrap().as_str().to_string(),
+                                None => {
+                                    return Err(format!(
+                                        "failed to extract return type of `initializer` in {}, it returns `Option<T>`",
+                                        path.trim_start_matches("./")
+                                    ))
+                                }
+                            }
+                        }
+                    };
+                    //println!(" {:?}", ctype);
+                    self.ctype = ctype;
+                }
+                handles.insert(key, value);
+            }
+            let module = Module { path, handles };
+            self.modules.insert(name.to_string(), module);
+        }
+        // an actor whose `entry_affix` always returns `None` generates no affix
+        let no_affix = regex::Regex::new(
+            r"(?s)fn\s+entry_affix\s*\([^)]*\)\s*->\s*Option<[^>]*>\s*\{\s*None\s*\}",
+        )
+        .unwrap();
+        self.has_affix = self.modules.contains_key("affix")
+            && match self.modules.get("actor") {
+                Some(actor) => std::fs::read_to_string(&actor.path)
+                    .map(|buf| !no_affix.is_match(&buf))
+                    .unwrap_or(true),
+                None => true,
+            };
+        Ok(())
+    }
+
+    pub fn get_pkg(&mut self) -> Result<(), String> {
+        let path = format!("{}/Cargo.toml", self.base_dir);
+        let file = std::fs::File::open(path)
+            .map_err(|_| "current directory must contain `Cargo.toml` file".to_string())?;
+        let mut pkgs = Vec::new();
+        let reader = BufReader::new(file);
+        let pat = regex::Regex::new(r"^\s*([\w|-]+)\s*=\s*").unwrap();
+        let pat1 = regex::Regex::new(r"^\s*path\s*=.*?src/bin/(?P<bin_name>[\w|-]+)\.rs").unwrap();
+        let pat2 = regex::Regex::new(r"^\s*\[dependencies\]").unwrap();
+        let pat3 = regex::Regex::new(r"^\s*\[.*?\]").unwrap();
+        let mut in_content = false;
+        let mut manifest = String::new();
+        for line in reader.lines() {
+            let text = line.map_err(|e| format!("failed to read Cargo.toml: {}", e))?;
+            manifest.push_str(&text);
+            manifest.push('\n');
+            if pat2.is_match(&text) {
+                in_content = true;
+            } else if !pat2.is_match(&text) && pat3.is_match(&text) {
+                in_content = false;
+            }
+            if in_content {
+                if let Some(t) = pat.captures(&text) {
+                    let pkg = t.get(1).unwrap().as_str().trim().replace("-", "_");
+                    pkgs.push(pkg)
+                }
+            }
+            if let Some(cap) = pat1.captures(&text) {
+                self.bin_name = cap["bin_name"].to_string();
+            }
+        }
+        // the library is named after `[lib] name`, or the package name with `-` replaced
+        self.package_name = util::get_manifest_value(&manifest, "lib", "name")
+            .or_else(|| util::get_manifest_value(&manifest, "package", "name"))
+            .map(|name| name.replace('-', "_"))
+            .ok_or("the name of package cannot be found in Cargo.toml")?;
+        self.dyer_version = util::get_dyer_version(&manifest);
+        // projects created before tokio has `signal` go without handling signals
+        let tokio = regex::Regex::new(r#"(?m)^\s*tokio\s*=.*"(signal|full)""#).unwrap();
+        self.has_signal = tokio.is_match(&manifest);
+        let dyer = regex::Regex::new(r#"(?m)^\s*dyer\s*=.*"(compression|full)""#).unwrap();
+        self.decompresses = dyer.is_match(&manifest);
+        self.grace_period = util::get_manifest_table(&manifest, "package.metadata.dyer")
+            .into_iter()
+            .find(|(key, _)| key == "grace-period")
+            .map(|(_, value)| value)
+            .unwrap_or_else(|| DEFAULT_GRACE_PERIOD.to_string());
+        if self.grace_period.parse::<u64>().is_err() {
+            return Err(format!(
+                "`grace-period` of `[package.metadata.dyer]` in Cargo.toml must be seconds, not `{}`",
+                self.grace_period
+            ));
+        }
+        self.serve = util::get_manifest_table(&manifest, "package.metadata.dyer")
+            .into_iter()
+            .find(|(key, _)| key == "serve")
+            .map(|(_, value)| value.trim_end_matches('/').to_string());
+        if let Some(origin) = self.serve.as_ref() {
+            let pat = regex::Regex::new(r"^http://[^/?#\s]+$").unwrap();
+            if !pat.is_match(origin) {
+                return Err(format!(
+                    "`serve` of `[package.metadata.dyer]` in Cargo.toml must be the origin of `dyer serve` such as `http://127.0.0.1:8000`, not `{}`",
+                    origin
+                ));
+            }
+        }
+        self.headers = util::get_configured_headers(&manifest)
+            .map_err(|e| format!("`[package.metadata.dyer.headers]` in Cargo.toml: {}", e))?;
+        if self.bin_name.is_empty() {
+            self.bin_name = self.package_name.clone();
+        }
+        self.pkgs.extend(pkgs);
+        //println!("packages: {:?}", self.pkgs);
+        Ok(())
+    }
+
+    fn complete_path(&self) -> bool {
+        let pieces = self
+            .ctype
+            .split("::")
+            .map(|piece| piece.trim())
+            .collect::<Vec<&str>>();
+        let subpath = pieces[0].to_string();
+        if !self.pkgs.contains(&subpath) {
+            return false;
+        }
+        true
+    }
+
+    pub fn get_pkg_list(&self) -> String {
+        let list = self
+            .pkgs
+            .iter()
+            .filter(|&ele| ele != "std")
+            .map(|md| format!("extern crate {};", md))
+            .collect::<Vec<String>>();
+        list.join("\n")
+    }
+
+    /// the files of modules that contain annotated items
+    pub fn get_paths(&self) -> Vec<String> {
+        let mut paths = self
+            .modules
+            .values()
+            .filter(|module| !module.handles.is_empty())
+            .map(|module| module.path.clone())
+            .collect::<Vec<String>>();
+        paths.sort();
+        paths
+    }
+
+    /// the path of the generated main
+    pub fn get_main_path(&self) -> String {
+        format!("{}src/bin/{}.rs", self.base_dir, self.bin_name)
+    }
+
+    /// the main to be written, with the user regions of the existing one,
+    /// `Err` if its generated portion is modified by hand
+    pub fn compose_main(&self, force: bool) -> Result<String, String> {
+        self.validate()?;
+        let main_str = self.render_main()?;
+        let old = match std::fs::read_to_string(self.get_main_path()) {
+            Ok(old) => old,
+            Err(_) => return Ok(main_str),
+        };
+        if !force {
+            self.check_unmodified(&old)?;
+        }
+        Ok(fill_regions(&main_str, &get_regions(&old)))
+    }
+
+    /// `Err` if `main`, the one in `src/bin/`, is modified outside of its user regions since it is
+    /// generated
+    pub(crate) fn check_unmodified(&self, main: &str) -> Result<(), String> {
+        match self.get_recorded_main() {
+            Some(recorded) if recorded != hash_generated(main) => Err(format!(
+                "{} is modified outside of its user regions, move the changes into them or run `dyer gen --force` to discard them",
+                self.get_main_path().trim_start_matches("./")
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// generate the main and record its fingerprint, refuse to overwrite a main modified by hand unless `force`
+    pub fn make_main(&self, force: bool) {
+        let main_str = self
+            .compose_main(force)
+            .unwrap_or_else(|e| util::exit_with(&e));
+        self.write_main(&main_str);
+    }
+
+    /// write the composed main and record its fingerprint, the main is left untouched when it is
+    /// the same, so that cargo does not rebuild it
+    pub(crate) fn write_main(&self, main_str: &str) {
+        let path = self.get_main_path();
+        if std::fs::read_to_string(&path).map_or(true, |old| old != main_str) {
+            let mut main_file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&path)
+                .unwrap();
+            main_file.write_all(main_str.as_bytes()).unwrap();
+        }
+        self.save_fingerprint(main_str);
+    }
+
+    /// the problems of the annotated items the main cannot be generated with, all of them in the message
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        // the signatures of unknown hooks are not checked
+        let mut problems = self.check_hooks();
+        if problems.is_empty() {
+            problems = self.check_signatures();
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(problems.join("\n"))
+    }
+
+    /// the attributes of middleware and pipeline naming no hook or an unknown one
+    pub(crate) fn check_hooks(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for name in ["middleware", "pipeline"].iter() {
+            let path = format!("src/{}.rs", name);
+            let buf = match std::fs::read_to_string(format!("{}{}", self.base_dir, path)) {
+                Ok(buf) => buf,
+                Err(_) => continue,
+            };
+            for item in get_items(&path, &buf)
+                .into_iter()
+                .filter(|item| item.module == *name)
+            {
+                if let Err(e) = check_hook(name, item.key.as_deref(), &item.ident) {
+                    problems.push(format!("{}:{}: {}", path, item.line, e));
+                }
+            }
+        }
+        problems
+    }
+
+    /// the hooks of middleware and pipeline whose functions do not have the signature dyer expects
+    pub(crate) fn check_signatures(&self) -> Vec<String> {
+        let entities = self
+            .modules
+            .get("entity")
+            .and_then(|entity| entity.handles.get("entities"))
+            .map(|entities| entities.as_str())
+            .unwrap_or("Entities");
+        let mut mismatches = Vec::new();
+        for name in ["middleware", "pipeline"].iter() {
+            let module = match self.modules.get(*name) {
+                Some(module) => module,
+                None => continue,
+            };
+            let buf = std::fs::read_to_string(&module.path).unwrap_or_default();
+            let path = module.path.trim_start_matches(&self.base_dir);
+            let mut handles = module.handles.iter().collect::<Vec<_>>();
+            handles.sort();
+            for (hook, ident) in handles {
+                let (params, ret) = match util::get_hook_signature(self.dyer_version, hook) {
+                    Some(signature) => signature,
+                    None => continue,
+                };
+                let signature = match parse_signature(&buf, ident) {
+                    Some(signature) => signature,
+                    None => continue,
+                };
+                let location = format!("{}:{}", path, signature.line);
+                if !signature.is_async {
+                    mismatches.push(format!(
+                        "{}: `{}` bound to `{}` must be an `async fn`",
+                        location, ident, hook
+                    ));
+                }
+                let expected = params
+                    .iter()
+                    .map(|p| replace_entities(p, entities))
+                    .collect::<Vec<String>>();
+                let matched = expected.len() == signature.params.len()
+                    && expected
+                        .iter()
+                        .zip(signature.params.iter())
+                        .all(|(e, p)| normalize_type(e) == normalize_type(p));
+                if !matched {
+                    mismatches.push(format!(
+                        "{}: `{}` expects `{}`, found `{}`",
+                        location,
+                        hook,
+                        expected.join(", "),
+                        signature.params.join(", ")
+                    ));
+                }
+                let found = normalize_type(&signature.ret);
+                let returned = match ret.strip_suffix("<_>") {
+                    Some(head) => found.starts_with(&format!("{}<", head)),
+                    None => found == normalize_type(ret),
+                };
+                if !returned {
+                    mismatches.push(format!(
+                        "{}: `{}` expects to return `{}`, found `{}`",
+                        location, hook, ret, signature.ret
+                    ));
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// the functions marked with `#[dyer::parser]`
+    pub(crate) fn get_parsers(&self) -> Vec<String> {
+        self.modules
+            .get("parser")
+            .map(|parser| parser.handles.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// the enum marked with `#[dyer::entity(entities)]`
+    pub(crate) fn get_entities(&self) -> String {
+        self.modules
+            .get("entity")
+            .and_then(|entity| entity.handles.get("entities"))
+            .cloned()
+            .unwrap_or_else(|| "Entities".into())
+    }
+
+    /// the call to start the cassette and its definition, `None` without `--record` or `--replay`
+    fn render_cassette(&self) -> Option<(String, String)> {
+        let (call, dir) = match self.cassette.as_ref()? {
+            Cassette::Record(dir) => ("cassette::start_recording();", dir),
+            Cassette::Replay(dir) => ("cassette::start_replaying();", dir),
+        };
+        let mut vars = HashMap::new();
+        vars.insert("cassette_dir".to_string(), format!("{:?}", dir));
+        // the body is saved whole, and decoded if dyer decompresses it
+        let framing = match self.decompresses {
+            true => r#""content-length", "transfer-encoding", "content-encoding""#,
+            false => r#""content-length", "transfer-encoding""#,
+        };
+        vars.insert("cassette_framing".to_string(), framing.to_string());
+        Some((call.to_string(), template::render_str(CASSETTE, &vars)))
+    }
+
+    /// what the main does in the middleware hooks besides the hooks of project: the hook, the code
+    /// run on its `items` and whether it runs before the hook of project
+    fn get_wrappings(&self) -> Vec<(&'static str, String, bool)> {
+        let mut wrappings = Vec::new();
+        let replaying = matches!(self.cassette, Some(Cassette::Replay(_)));
+        match self.cassette {
+            // the responses are recorded as they are received
+            // the requests are noted as they are sent, after the hook of project
+            Some(Cassette::Record(_)) => {
+                let record = "items.iter().for_each(cassette::record);";
+                wrappings.push(("handle_req", "cassette::note(items);".to_string(), false));
+                wrappings.push(("handle_res", record.to_string(), true));
+                wrappings.push((
+                    "handle_err",
+                    "cassette::record_results(items);".to_string(),
+                    true,
+                ));
+            }
+            // the requests are redirected once the hook of project is done with them
+            Some(Cassette::Replay(_)) => {
+                wrappings.push(("handle_req", "cassette::replay(items);".to_string(), false))
+            }
+            None => {}
+        }
+        if !self.headers.is_empty() {
+            let headers = self
+                .headers
+                .iter()
+                .map(|(name, value)| format!("({:?}, {:?})", name, value))
+                .collect::<Vec<String>>();
+            let set = format!("set_headers(items, &[{}]);", headers.join(", "));
+            // set before the requests are redirected, after the hook of project is done with them
+            wrappings.insert(0, ("handle_req", set, false));
+        }
+        // the cassette serves the responses in place of `dyer serve`
+        if let Some(origin) = self.serve.as_ref().filter(|_| !replaying) {
+            let serve = format!("serve_locally(items, {:?});", origin);
+            wrappings.push(("handle_req", serve, false));
+        }
+        wrappings
+    }
+
+    /// the functions wrapping the middleware hooks, which replace them in `handles`
+    fn render_wrapped_hooks(
+        &self,
+        entities: &str,
+        handles: &mut HashMap<String, String>,
+    ) -> String {
+        let wrappings = self.get_wrappings();
+        let mut hooks = wrappings
+            .iter()
+            .map(|(hook, _, _)| *hook)
+            .collect::<Vec<&str>>();
+        hooks.sort_unstable();
+        hooks.dedup();
+        let mut wrappers = Vec::new();
+        for hook in hooks {
+            let name = format!("wrapped_{}", hook);
+            let code = |before: bool| {
+                wrappings
+                    .iter()
+                    .filter(move |(h, _, b)| *h == hook && *b == before)
+                    .map(|(_, code, _)| code.clone())
+            };
+            let mut lines = code(true).collect::<Vec<String>>();
+            if let Some(func) = handles.insert(hook.to_string(), name.clone()) {
+                lines.push(format!("{}(items, _app).await;", func));
+            }
+            lines.extend(code(false));
+            let (params, _) = util::get_hook_signature(self.dyer_version, hook).unwrap();
+            let items = replace_entities(params[0], entities).replacen("&", "&'a ", 1);
+            wrappers.push(format!(
+                "fn {}<'a>(items: {}, _app: &'a mut App<{}>) -> BoxFuture<'a, ()> {{\n    Box::pin(async move {{\n        {}\n    }})\n}}",
+                name,
+                items,
+                entities,
+                lines.join("\n        ")
+            ));
+        }
+        if wrappings
+            .iter()
+            .any(|(_, code, _)| code.starts_with("serve_locally"))
+        {
+            wrappers.push(SERVE_LOCALLY.trim_start().to_string());
+        }
+        if !self.headers.is_empty() {
+            wrappers.push(SET_HEADERS.trim_start().to_string());
+        }
+        wrappers.join("\n\n")
+    }
+
+    /// the content of the main generated from the annotated items, `Err` if the items or the
+    /// template of project cannot make one
+    pub fn render_main(&self) -> Result<String, String> {
+        let entities = self
+            .modules
+            .get("entity")
+            .and_then(|entity| entity.handles.get("entities"))
+            .ok_or("no enum is annotated with `#[dyer::entity(entities)]` in src/entity.rs")?;
+        let actor = self
+            .modules
+            .get("actor")
+            .and_then(|actor| actor.handles.values().next())
+            .ok_or("no struct is annotated with `#[dyer::actor]` in src/actor.rs")?;
+        let get_middleware_list = self.modules.get("middleware").unwrap().get_list();
+        let get_pipeline_list = self.modules.get("pipeline").unwrap().get_list();
+        let get_pipeline_map = self
+            .modules
+            .get("pipeline")
+            .unwrap()
+            .get_map(self.has_affix);
+        let mut middleware = self.modules.get("middleware").unwrap().handles.clone();
+        let wrapped_hooks = self.render_wrapped_hooks(entities, &mut middleware);
+        let get_middleware_map = Module {
+            path: String::new(),
+            handles: middleware,
+        }
+        .get_map(self.has_affix);
+        let ctype = if self.complete_path() {
+            &self.ctype
+        } else {
+            "_"
+        };
+        let get_pkg_list = self.get_pkg_list();
+        let package_name = &self.package_name;
+
+        let marker_expr =
+            util::get_version_rule(self.dyer_version, "marker").replace("<+marker+>", actor);
+        let mut vars = HashMap::new();
+        vars.insert("package_name", package_name.to_string());
+        vars.insert("entities", entities.to_string());
+        vars.insert("actor", actor.to_string());
+        vars.insert("marker", actor.to_string());
+        vars.insert("marker_expr", marker_expr);
+        vars.insert("get_pkg_list", get_pkg_list);
+        vars.insert("get_middleware_list", get_middleware_list);
+        vars.insert("get_middleware_map", get_middleware_map);
+        vars.insert("get_pipeline_list", get_pipeline_list);
+        vars.insert("get_pipeline_map", get_pipeline_map);
+        vars.insert("ctype", ctype.to_string());
+        vars.insert("bin_name", self.bin_name.clone());
+        vars.insert("dyer_version", self.dyer_version.to_string());
+        vars.insert("log_level", "log::LevelFilter::Info".to_string());
+        vars.insert("runtime", "#[tokio::main]".to_string());
+        vars.insert("config_path", "dyer.cfg".to_string());
+        vars.insert("grace_period", self.grace_period.clone());
+        if self.has_signal {
+            let call = format!(
+                "handle_signals(std::time::Duration::from_secs({}));",
+                self.grace_period
+            );
+            vars.insert("handle_signals_call", call);
+            vars.insert("handle_signals", HANDLE_SIGNALS.to_string());
+        } else {
+            vars.insert("handle_signals_call", String::new());
+            vars.insert("handle_signals", String::new());
+        }
+        let (cassette_call, cassette) = self.render_cassette().unwrap_or_default();
+        vars.insert("cassette_call", cassette_call);
+        vars.insert("cassette", cassette);
+        vars.insert("wrapped_hooks", wrapped_hooks.clone());
+        let mut vars = vars
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<HashMap<String, String>>();
+        let manifest = std::fs::read_to_string(format!("{}Cargo.toml", self.base_dir))
+            .map_err(|e| format!("failed to read Cargo.toml: {}", e))?;
+        for (key, value) in util::get_manifest_table(&manifest, "package.metadata.dyer.vars") {
+            vars.insert(format!("var.{}", key), value);
+        }
+
+        let path = format!("{}{}", self.base_dir, MAIN_TEMPLATE_PATH);
+        let main_str = std::fs::read_to_string(&path).unwrap_or_else(|_| MAIN_TEMPLATE.to_string());
+        if self.cassette.is_some()
+            && !(main_str.contains("<+cassette+>") && main_str.contains("<+cassette_call+>"))
+        {
+            return Err(format!(
+                "{} cannot record or replay responses, put <+cassette_call+> before `app.run(..)` and <+cassette+> after the main",
+                MAIN_TEMPLATE_PATH
+            ));
+        }
+        if !wrapped_hooks.is_empty() && !main_str.contains("<+wrapped_hooks+>") {
+            return Err(format!(
+                "{} cannot wrap the middleware hooks for `dyer run --record`, `--replay`, `serve` or `headers`, put <+wrapped_hooks+> after the main",
+                MAIN_TEMPLATE_PATH
+            ));
+        }
+        let placeholder = regex::Regex::new(r"<\+(?P<key>[A-Za-z_][\w.-]*)\+>").unwrap();
+        let mut unknown = placeholder
+            .captures_iter(&main_str)
+            .map(|cap| cap["key"].to_string())
+            .filter(|key| {
+                !vars.contains_key(key) && !REGIONS.iter().any(|r| key == &format!("region_{}", r))
+            })
+            .collect::<Vec<String>>();
+        if !unknown.is_empty() {
+            unknown.sort();
+            unknown.dedup();
+            let mut known = vars.keys().cloned().collect::<Vec<String>>();
+            known.extend(REGIONS.iter().map(|r| format!("region_{}", r)));
+            known.sort();
+            return Err(format!(
+                "unknown placeholder <+{}+> in {}, available ones: {}",
+                unknown.join("+>, <+"),
+                MAIN_TEMPLATE_PATH,
+                known.join(", ")
+            ));
+        }
+        for region in REGIONS.iter() {
+            let markers = format!("{}\n{}", region_begin(region), region_end(region));
+            vars.insert(format!("region_{}", region), markers);
+        }
+        // the lines of a value are indented as its placeholder, a line of an empty value alone is removed
+        Ok(main_str
+            .split('\n')
+            .filter(|line| {
+                let key = line.trim().trim_start_matches("<+").trim_end_matches("+>");
+                !(line.trim().starts_with("<+")
+                    && vars.get(key).map(|v| v.is_empty()).unwrap_or(false))
+            })
+            .map(|line| {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                if indent.is_empty() || !line.contains("<+") {
+                    return template::render_str(line, &vars);
+                }
+                let indented = vars
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.replace('\n', &format!("\n{}", indent))))
+                    .collect::<HashMap<String, String>>();
+                template::render_str(line, &indented)
+            })
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+}
+
+#[derive(Debug)]
+struct Module {
+    path: String,
+    handles: HashMap<String, String>,
+}
+
+impl Module {
+    pub fn get_list(&self) -> String {
+        self.handles
+            .values()
+            .map(|val| val.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+
+    pub fn get_map(&self, has_affix: bool) -> String {
+        let mut ms = Vec::new();
+        for (key, val) in self.handles.iter() {
+            if key == "handle_affix" && !has_affix {
+                continue;
+            }
+            let output = match key.as_str() {
+                "initializer" => format!(".initializer(&{})", val),
+                "disposer" => format!(".disposer(&{})", val),
+                "process_entity" => format!(".entity(&{})", val),
+                "process_yerr" => format!(".yerr(&{})", val),
+                "handle_task" => format!(".task(&{})", val),
+                "handle_affix" => format!(".affix(&{})", val),
+                "handle_entity" => format!(".entity(&{})", val),
+                "handle_req" => format!(".req(&{})", val),
+                "handle_res" => format!(".res(&{})", val),
+                "handle_err" => format!(".err(&{})", val),
+                "handle_yerr" => format!(".yerr(&{})", val),
+                // reported by `MetaData::check_hooks` before
+                _ => continue,
+            };
+            ms.push(output);
+        }
+        // chained calls of the builder, in a stable order
+        ms.sort();
+        ms.join("\n")
+    }
+}
+
+/// generate the main in `src/bin/` unless it is made from the current inputs, whether it is generated
+pub(crate) fn ensure_main(cassette: Option<Cassette>) -> bool {
+    let mut meta = MetaData::new();
+    meta.cassette = cassette;
+    match try_ensure_main(&mut meta) {
+        Ok(Some(_)) => {
+            println!("    initializing the main function inside src/bin/ ...");
+            true
+        }
+        Ok(None) => false,
+        Err(e) => util::exit_with(&e),
+    }
+}
+
+/// discover the project with the settings of `meta` and generate the main unless it is made from
+/// the current inputs, the main it replaces(empty if there is none) once it is generated, `Err`
+/// on a project the main cannot be generated for
+pub(crate) fn try_ensure_main(meta: &mut MetaData) -> Result<Option<String>, String> {
+    meta.try_init()?;
+    if meta.is_fresh() {
+        return Ok(None);
+    }
+    let main_str = meta.compose_main(false)?;
+    let old = std::fs::read_to_string(meta.get_main_path()).unwrap_or_default();
+    meta.write_main(&main_str);
+    Ok(Some(old))
+}
+
+impl SubComRun {
+    pub fn execute(&self) {
+        if let Some(Cassette::Replay(dir)) = self.cassette.as_ref() {
+            if !std::path::Path::new(&format!("{}/index", dir)).exists() {
+                util::exit_with(&format!(
+                    "no cassette is recorded in {}, run `dyer run --record {}` first",
+                    dir, dir
+                ));
+            }
+        }
+        ensure_main(self.cassette.clone());
+        let options = self
+            .options
+            .iter()
+            .map(|op| op.as_str())
+            .filter(|op| {
+                if ["--off", "--error", "--warn", "--info", "--debug", "--trace"].contains(op) {
+                    util::change_log_level(op);
+                    return false;
+                }
+                true
+            })
+            .collect::<Vec<&str>>();
+        let mut args = vec!["run"];
+        args.extend(options);
+        if !util::run_command("cargo", args) {
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a project in a temporary directory, removed when dropped
+    struct Project(std::path::PathBuf);
+
+    impl Project {
+        fn new(name: &str, middleware: &str, pipeline: &str) -> Project {
+            let dir =
+                std::env::temp_dir().join(format!("dyer-run-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(dir.join("src/bin")).unwrap();
+            let files = [
+                (
+                    "Cargo.toml",
+                    "[package]\nname = \"demo\"\n\n[[bin]]\nname = \"demo\"\npath = \"src/bin/demo.rs\"\n\n[dependencies]\ndyer = \"3.3\"\n",
+                ),
+                ("src/actor.rs", "#[dyer::actor]\npub struct MyActor {}\n"),
+                ("src/entity.rs", "#[dyer::entity(entities)]\npub enum Entities {}\n"),
+                ("src/parser.rs", ""),
+                ("src/middleware.rs", middleware),
+                ("src/pipeline.rs", pipeline),
+            ];
+            for (path, buf) in files.iter() {
+                std::fs::write(dir.join(path), buf).unwrap();
+            }
+            Project(dir)
+        }
+
+        fn meta(&self) -> MetaData {
+            let mut meta = MetaData::new();
+            meta.base_dir = format!("{}/", self.0.display());
+            meta.try_init().unwrap();
+            meta
+        }
+    }
+
+    impl Drop for Project {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const INITIALIZER: &str =
+        "#[dyer::pipeline(initializer)]\nasync fn open(_app: &mut App<Entities>) -> Option<std::fs::File> {\n    None\n}\n";
+
+    #[test]
+    fn parse_signature_reads_the_parameters_and_the_return_type() {
+        let buf = "use dyer::*;\n\n#[dyer::middleware(handle_task)]\npub async fn handle_tasks<'a, T: Into<Vec<u8>>>(\n    mut tasks: &'a mut Vec<Task>,\n    (_a, _b): (u8, u8),\n    f: impl Fn(u8) -> Result<u8, ()>,\n    _app: &mut dyer::App<HashMap<String, Vec<u8>>>,\n) -> Option<std::fs::File>\nwhere\n    T: Clone,\n{\n}\n";
+        let signature = parse_signature(buf, "handle_tasks").unwrap();
+        assert!(signature.is_async);
+        assert_eq!(signature.line, 4);
+        assert_eq!(
+            signature.params,
+            vec![
+                "&'a mut Vec<Task>",
+                "(u8, u8)",
+                "impl Fn(u8) -> Result<u8, ()>",
+                "&mut dyer::App<HashMap<String, Vec<u8>>>",
+            ]
+        );
+        assert_eq!(signature.ret, "Option<std::fs::File>");
+
+        let signature = parse_signature("fn dispose(_app: &mut App<E>);", "dispose").unwrap();
+        assert!(!signature.is_async);
+        assert_eq!(signature.ret, "()");
+        assert!(parse_signature(buf, "handle").is_none());
+        assert!(parse_signature(buf, "handle_tasks_v2").is_none());
+    }
+
+    #[test]
+    fn split_top_level_splits_by_the_commas_outside_of_brackets() {
+        assert_eq!(
+            split_top_level("a: HashMap<u8, Vec<(u8, u8)>>, b: [u8; 2], c: impl Fn(u8, u8) -> u8,"),
+            vec![
+                "a: HashMap<u8, Vec<(u8, u8)>>",
+                "b: [u8; 2]",
+                "c: impl Fn(u8, u8) -> u8"
+            ]
+        );
+        assert!(split_top_level(" , ").is_empty());
+    }
+
+    #[test]
+    fn normalize_type_ignores_lifetimes_paths_and_spaces() {
+        assert_eq!(
+            normalize_type("&'a mut std::vec::Vec< dyer::Task >"),
+            normalize_type("&mut Vec<Task>")
+        );
+        assert_eq!(normalize_type("&'static str"), "&str");
+        assert_ne!(
+            normalize_type("&Vec<Task>"),
+            normalize_type("&mut Vec<Task>")
+        );
+    }
+
+    #[test]
+    fn check_signatures_accepts_the_signatures_dyer_expects() {
+        let middleware = "use dyer::*;\n#[dyer::middleware(handle_task)]\npub async fn handle_tasks<'a>(\n    tasks: &'a mut std::vec::Vec<dyer::Task>,\n    _app: &'a mut App<crate::entity::Entities>,\n) {\n}\n#[dyer::middleware(handle_err)]\npub async fn handle_errs(_: &mut Vec<Result<Response, MetaResponse>>, _: &mut App<Entities>) -> () {}\n";
+        let pipeline = format!(
+            "{}#[dyer::pipeline(process_entity)]\nasync fn store(items: Vec<Entities>, app: &mut App<Entities>) {{}}\n",
+            INITIALIZER
+        );
+        let project = Project::new("accepted", middleware, &pipeline);
+        assert_eq!(project.meta().check_signatures(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn check_signatures_rejects_the_other_signatures() {
+        let middleware = "#[dyer::middleware(handle_task)]\npub fn handle_tasks(_tasks: &mut Vec<Task>, _app: &mut App<Entities>) {}\n#[dyer::middleware(handle_req)]\npub async fn handle_reqs(_reqs: &Vec<Request>, _app: &mut App<Entities>) {}\n";
+        let pipeline = format!(
+            "{}#[dyer::pipeline(disposer)]\nasync fn close(_app: &mut App<Entities>) -> bool {{\n    true\n}}\n",
+            INITIALIZER
+        );
+        let project = Project::new("rejected", middleware, &pipeline);
+        assert_eq!(
+            project.meta().check_signatures(),
+            vec![
+                "src/middleware.rs:4: `handle_req` expects `&mut Vec<Request>, &mut App<Entities>`, found `&Vec<Request>, &mut App<Entities>`".to_string(),
+                "src/middleware.rs:2: `handle_tasks` bound to `handle_task` must be an `async fn`".to_string(),
+                "src/pipeline.rs:6: `disposer` expects to return `()`, found `bool`".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_hook_suggests_the_closest_hook() {
+        assert_eq!(
+            check_hook("middleware", Some("handle_req"), "handle_reqs"),
+            Ok(())
+        );
+        assert_eq!(check_hook("parser", None, "parse"), Ok(()));
+        let e = check_hook("middleware", Some("handle_reqs"), "handle").unwrap_err();
+        assert!(e.starts_with(
+            "unknown hook `handle_reqs` of `#[dyer::middleware]` on `handle`, did you mean `handle_req`? valid hooks: handle_task, "
+        ));
+        let e = check_hook("pipeline", Some("initialiser"), "open").unwrap_err();
+        assert!(e.contains("did you mean `initializer`?"));
+    }
+
+    #[test]
+    fn check_hook_lists_the_hooks_without_a_close_one() {
+        let e = check_hook("pipeline", Some("on_start"), "open").unwrap_err();
+        assert_eq!(
+            e,
+            "unknown hook `on_start` of `#[dyer::pipeline]` on `open`, valid hooks: initializer, disposer, process_entity, process_yerr"
+        );
+        let e = check_hook("middleware", None, "handle").unwrap_err();
+        assert!(e.starts_with(
+            "`#[dyer::middleware]` on `handle` names no hook, eg. `#[dyer::middleware(handle_task)]`, valid hooks: "
+        ));
+    }
+
+    #[test]
+    fn check_hooks_reports_the_unknown_and_missing_keys_of_a_project() {
+        let middleware = "#[dyer::middleware]\npub async fn handle(_tasks: &mut Vec<Task>, _app: &mut App<Entities>) {}\n";
+        let pipeline = format!(
+            "{}#[dyer::pipeline(dispose)]\nasync fn close(_app: &mut App<Entities>) {{}}\n",
+            INITIALIZER
+        );
+        let project = Project::new("hooks", middleware, &pipeline);
+        let meta = project.meta();
+        let problems = meta.check_hooks();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0]
+            .starts_with("src/middleware.rs:1: `#[dyer::middleware]` on `handle` names no hook"));
+        assert!(problems[1].starts_with(
+            "src/pipeline.rs:5: unknown hook `dispose` of `#[dyer::pipeline]` on `close`, did you mean `disposer`?"
+        ));
+        assert_eq!(meta.compose_main(false).unwrap_err(), problems.join("\n"));
+    }
+
+    #[test]
+    fn fill_regions_keeps_the_markers_and_puts_the_content_between_them() {
+        let main = format!(
+            "use dyer::*;\n{}\n{}\nfn main() {{}}\n",
+            region_begin("imports"),
+            region_end("imports")
+        );
+        assert!(get_regions(&main)
+            .values()
+            .all(|content| content.is_empty()));
+        assert_eq!(fill_regions(&main, &HashMap::new()), main);
+        let mut regions = HashMap::new();
+        regions.insert(
+            "imports".to_string(),
+            "use std::io;\nuse std::fs;".to_string(),
+        );
+        let filled = fill_regions(&main, &regions);
+        assert_eq!(
+            filled,
+            format!(
+                "use dyer::*;\n{}\nuse std::io;\nuse std::fs;\n{}\nfn main() {{}}\n",
+                region_begin("imports"),
+                region_end("imports")
+            )
+        );
+        assert_eq!(get_regions(&filled), regions);
+        assert_eq!(fill_regions(&filled, &HashMap::new()), main);
+        assert_eq!(hash_generated(&filled), hash_generated(&main));
+        let relevelled = main.replace("fn main", "// log::LevelFilter::Debug\nfn main");
+        let other = main.replace("fn main", "// log::LevelFilter::Info\nfn main");
+        assert_eq!(hash_generated(&relevelled), hash_generated(&other));
+        assert_ne!(hash_generated(&relevelled), hash_generated(&main));
+    }
+
+    #[test]
+    fn compose_main_keeps_the_user_regions_when_regenerated() {
+        let project = Project::new("regions", "", INITIALIZER);
+        let meta = project.meta();
+        let main = meta.compose_main(false).unwrap();
+        let begin = region_begin("before-run");
+        assert_eq!(main.matches(&begin).count(), 1);
+        let edited = main.replacen(&begin, &format!("{}\n    let retries = 3;", begin), 1);
+        meta.write_main(&main);
+        std::fs::write(meta.get_main_path(), &edited).unwrap();
+        let regenerated = meta.compose_main(false).unwrap();
+        assert_eq!(regenerated, edited);
+        assert_eq!(
+            get_regions(&regenerated)["before-run"],
+            "    let retries = 3;"
+        );
+    }
+
+    #[test]
+    fn compose_main_refuses_to_overwrite_the_edits_outside_of_the_user_regions() {
+        let project = Project::new("edits", "", INITIALIZER);
+        let meta = project.meta();
+        let main = meta.compose_main(false).unwrap();
+        meta.write_main(&main);
+        let begin = region_begin("after-run");
+        let edited = main
+            .replacen(&begin, &format!("{}\n    println!(\"done\");", begin), 1)
+            .replacen(
+                "async fn main() {",
+                "async fn main() {\n    println!(\"start\");",
+                1,
+            );
+        assert_ne!(edited, main);
+        std::fs::write(meta.get_main_path(), &edited).unwrap();
+        let e = meta.compose_main(false).unwrap_err();
+        assert!(
+            e.contains("is modified outside of its user regions"),
+            "{}",
+            e
+        );
+        // forced, the edit is discarded and the user regions are still kept
+        let forced = meta.compose_main(true).unwrap();
+        assert!(!forced.contains("println!(\"start\")"));
+        assert_eq!(get_regions(&forced)["after-run"], "    println!(\"done\");");
+    }
+}
@@ -0,0 +1,475 @@
+use crate::util;
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// the manifest in the served directory, how the pages are served
+const MANIFEST: &str = "serve.toml";
+/// the port served on unless `--port` or `serve` of `[package.metadata.dyer]` gives one
+const DEFAULT_PORT: &str = "8000";
+/// keys of a page in the manifest, along with `header.<name>`
+const RULE_KEYS: [&str; 4] = ["status", "delay", "redirect", "file"];
+
+// dyer serve <dir> [--port <port>]
+#[derive(std::fmt::Debug)]
+pub struct SubComServe {
+    // the directory of saved pages
+    pub dir: String,
+    pub port: Option<String>,
+}
+
+/// how a page is served, a section `["/path"]` of the manifest
+#[derive(std::fmt::Debug, Default, Clone)]
+struct Rule {
+    // the path with the query if any, the pages under it if it ends with `*`
+    path: String,
+    status: Option<u16>,
+    // seconds to wait before responding
+    delay: Option<f64>,
+    // the location redirected to
+    redirect: Option<String>,
+    // the file served instead of the one at the path, relative to the directory
+    file: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl SubComServe {
+    pub fn execute(&self) {
+        if !std::path::Path::new(&self.dir).is_dir() {
+            util::exit_with(&format!("{} is not a directory of saved pages", self.dir));
+        }
+        let rules = get_rules(&self.dir).unwrap_or_else(|e| util::exit_with(&e));
+        let port = self
+            .port
+            .clone()
+            .or_else(get_configured_port)
+            .unwrap_or_else(|| DEFAULT_PORT.to_string());
+        let addr = format!("127.0.0.1:{}", port);
+        let listener = std::net::TcpListener::bind(&addr)
+            .unwrap_or_else(|e| util::exit_with(&format!("cannot listen on {}: {}", addr, e)));
+        println!(
+            "    serving {} on http://{}, {} page(s) configured in {}",
+            self.dir,
+            addr,
+            rules.len(),
+            MANIFEST
+        );
+        for stream in listener.incoming().filter_map(|s| s.ok()) {
+            let dir = self.dir.clone();
+            std::thread::spawn(move || serve(stream, &dir));
+        }
+    }
+}
+
+/// the port of `serve` in `[package.metadata.dyer]` if run inside a project
+fn get_configured_port() -> Option<String> {
+    let manifest = std::fs::read_to_string("Cargo.toml").ok()?;
+    let (_, origin) = util::get_manifest_table(&manifest, "package.metadata.dyer")
+        .into_iter()
+        .find(|(key, _)| key == "serve")?;
+    let (_, port) = origin.trim_end_matches('/').rsplit_once(':')?;
+    port.parse::<u16>().ok().map(|port| port.to_string())
+}
+
+/// the pages of the manifest in `dir`, none if it does not exist
+fn get_rules(dir: &str) -> Result<Vec<Rule>, String> {
+    let path = format!("{}/{}", dir.trim_end_matches('/'), MANIFEST);
+    let buf = match std::fs::read_to_string(&path) {
+        Ok(buf) => buf,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let header = regex::Regex::new(r#"^\s*\[\s*"(?P<path>[^"]*)"\s*\]\s*$"#).unwrap();
+    let pair = regex::Regex::new(r"^\s*(?P<key>[\w.-]+)\s*=\s*(?P<value>.*?)\s*$").unwrap();
+    let mut rules: Vec<Rule> = Vec::new();
+    for (i, line) in buf.lines().enumerate() {
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let problem = |message: String| format!("{}:{}: {}", path, i + 1, message);
+        if let Some(cap) = header.captures(line) {
+            rules.push(Rule {
+                path: cap["path"].to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+        let cap = pair.captures(line).ok_or_else(|| {
+            problem(format!(
+                "expected `[\"/path\"]` or `key = value`, found `{}`",
+                text
+            ))
+        })?;
+        let rule = rules
+            .last_mut()
+            .ok_or_else(|| problem("the page is not given, start it with `[\"/path\"]`".into()))?;
+        let key = &cap["key"];
+        let value = cap["value"].trim_matches(|c| c == '"' || c == '\'');
+        match key {
+            "status" => {
+                let status = value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|status| (100..600).contains(status))
+                    .ok_or_else(|| {
+                        problem(format!("`status` is `{}`, eg. `status = 404`", value))
+                    })?;
+                rule.status = Some(status);
+            }
+            "delay" => {
+                let delay = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|delay| *delay >= 0.0)
+                    .ok_or_else(|| {
+                        problem(format!(
+                            "`delay` is `{}`, set it in seconds, eg. `delay = 1.5`",
+                            value
+                        ))
+                    })?;
+                rule.delay = Some(delay);
+            }
+            "redirect" => rule.redirect = Some(value.to_string()),
+            "file" => rule.file = Some(value.to_string()),
+            _ if key.starts_with("header.") => {
+                rule.headers
+                    .push((key["header.".len()..].to_string(), value.to_string()));
+            }
+            _ => {
+                let suggestion = util::get_closest(key, &RULE_KEYS)
+                    .map(|k| format!(", did you mean `{}`?", k))
+                    .unwrap_or_else(|| ",".into());
+                return Err(problem(format!(
+                    "unknown key `{}`{} valid keys: {}, header.<name>",
+                    key,
+                    suggestion,
+                    RULE_KEYS.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// the page of `target`, the path with query: the exact one, the path without query, or the
+/// longest prefix ending with `*`
+fn find_rule<'a>(rules: &'a [Rule], target: &str) -> Option<&'a Rule> {
+    let path = target.split('?').next().unwrap_or(target);
+    rules
+        .iter()
+        .find(|rule| rule.path == target)
+        .or_else(|| rules.iter().find(|rule| rule.path == path))
+        .or_else(|| {
+            rules
+                .iter()
+                .filter_map(|rule| {
+                    let prefix = rule.path.strip_suffix('*')?;
+                    target.starts_with(prefix).then_some((prefix.len(), rule))
+                })
+                .max_by_key(|(len, _)| *len)
+                .map(|(_, rule)| rule)
+        })
+}
+
+/// answer a request of the connection and close it
+fn serve(stream: std::net::TcpStream, dir: &str) {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    if reader.read_line(&mut line).unwrap_or(0) == 0 {
+        return;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("GET").to_string();
+    let target = parts.next().unwrap_or("/").to_string();
+    // the body of request is read but not used
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).unwrap_or(0) == 0 {
+            return;
+        }
+        match header.trim().split_once(':') {
+            Some((key, value)) if key.eq_ignore_ascii_case("content-length") => {
+                length = value.trim().parse().unwrap_or(0);
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    let mut body = vec![0; length];
+    if reader.read_exact(&mut body).is_err() {
+        return;
+    }
+
+    let (status, headers, body) = respond(dir, &target);
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\ncontent-length: {}\r\nconnection: close\r\n",
+        status,
+        get_reason(status),
+        body.len()
+    );
+    for (key, value) in headers.iter() {
+        head.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    head.push_str("\r\n");
+    let mut stream = reader.into_inner();
+    let written = stream
+        .write_all(head.as_bytes())
+        .and_then(|_| match method.as_str() {
+            "HEAD" => Ok(()),
+            _ => stream.write_all(&body),
+        });
+    if written.is_ok() {
+        println!("    {} {} {} {}B", method, target, status, body.len());
+    }
+}
+
+/// the status, headers and body answering `target`
+fn respond(dir: &str, target: &str) -> (u16, Vec<(String, String)>, Vec<u8>) {
+    // the manifest is read every time so that changes take effect at once
+    let rules = match get_rules(dir) {
+        Ok(rules) => rules,
+        Err(e) => {
+            println!("    {}", e);
+            let headers = vec![("content-type".into(), "text/plain".into())];
+            return (500, headers, e.into_bytes());
+        }
+    };
+    let rule = find_rule(&rules, target).cloned().unwrap_or_default();
+    if let Some(delay) = rule.delay {
+        std::thread::sleep(std::time::Duration::from_secs_f64(delay));
+    }
+    let mut headers = Vec::new();
+    let (status, body) = if let Some(location) = rule.redirect.as_ref() {
+        headers.push(("location".to_string(), location.clone()));
+        (rule.status.unwrap_or(302), Vec::new())
+    } else {
+        let path = match rule.file.as_ref() {
+            Some(file) => file.clone(),
+            None => decode(target.split('?').next().unwrap_or(target)),
+        };
+        match get_file(dir, &path).and_then(|file| std::fs::read(&file).ok().map(|b| (file, b))) {
+            Some((file, body)) => {
+                headers.push((
+                    "content-type".to_string(),
+                    get_content_type(&file).to_string(),
+                ));
+                (rule.status.unwrap_or(200), body)
+            }
+            None => {
+                headers.push(("content-type".to_string(), "text/plain".to_string()));
+                let body = format!("{} is not found in {}", path, dir).into_bytes();
+                (rule.status.unwrap_or(404), body)
+            }
+        }
+    };
+    // the headers of the page replace the default ones
+    headers.retain(|(key, _)| {
+        !rule
+            .headers
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case(key))
+    });
+    headers.extend(rule.headers);
+    (status, headers, body)
+}
+
+/// the file of `path` inside `dir`, `index.html` of a directory
+fn get_file(dir: &str, path: &str) -> Option<std::path::PathBuf> {
+    let mut file = std::path::PathBuf::from(dir);
+    for piece in path.split('/').filter(|p| !p.is_empty() && *p != ".") {
+        // never outside of the directory
+        if piece == ".." {
+            return None;
+        }
+        file.push(piece);
+    }
+    if file.is_dir() {
+        file.push("index.html");
+    }
+    Some(file).filter(|file| file.is_file())
+}
+
+/// `path` with `%XX` decoded
+fn decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = path
+            .get(i + 1..i + 3)
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn get_content_type(file: &std::path::Path) -> &'static str {
+    let ext = file
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv",
+        "css" => "text/css",
+        "js" => "application/javascript",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+fn get_reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        410 => "Gone",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Status",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(path: &str, status: u16) -> Rule {
+        Rule {
+            path: path.into(),
+            status: Some(status),
+            ..Rule::default()
+        }
+    }
+
+    /// a directory of saved pages removed when dropped
+    struct Pages(std::path::PathBuf);
+
+    impl Pages {
+        fn new(name: &str) -> Pages {
+            let dir =
+                std::env::temp_dir().join(format!("dyer-serve-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(dir.join("list")).unwrap();
+            std::fs::write(dir.join("list/index.html"), "list").unwrap();
+            std::fs::write(dir.join("a b.json"), "{}").unwrap();
+            Pages(dir)
+        }
+
+        fn dir(&self) -> String {
+            self.0.to_string_lossy().to_string()
+        }
+    }
+
+    impl Drop for Pages {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn find_rule_prefers_the_exact_target_then_the_path_then_the_longest_prefix() {
+        let rules = vec![
+            rule("/items*", 1),
+            rule("/items?page=2", 2),
+            rule("/items", 3),
+            rule("/items/new*", 4),
+            rule("*", 5),
+        ];
+        let status = |target: &str| find_rule(&rules, target).and_then(|rule| rule.status);
+        assert_eq!(status("/items?page=2"), Some(2));
+        assert_eq!(status("/items?page=3"), Some(3));
+        assert_eq!(status("/items"), Some(3));
+        assert_eq!(status("/items/1"), Some(1));
+        assert_eq!(status("/items/new/1?draft"), Some(4));
+        assert_eq!(status("/other"), Some(5));
+        assert!(find_rule(&rules[..4], "/other").is_none());
+    }
+
+    #[test]
+    fn decode_takes_percent_encoded_bytes() {
+        assert_eq!(decode("/a%20b.json"), "/a b.json");
+        assert_eq!(decode("/caf%C3%A9"), "/caf\u{e9}");
+        assert_eq!(decode("/%2e%2E/x"), "/../x");
+        assert_eq!(decode("/100%"), "/100%");
+        assert_eq!(decode("/%zz%4"), "/%zz%4");
+        assert_eq!(decode("/a+b"), "/a+b");
+    }
+
+    #[test]
+    fn get_file_stays_inside_the_directory() {
+        let pages = Pages::new("files");
+        let dir = pages.dir();
+        assert_eq!(
+            get_file(&dir, "/list/"),
+            Some(pages.0.join("list/index.html"))
+        );
+        assert_eq!(
+            get_file(&dir, "/./list"),
+            Some(pages.0.join("list/index.html"))
+        );
+        assert_eq!(
+            get_file(&dir, &decode("/a%20b.json")),
+            Some(pages.0.join("a b.json"))
+        );
+        assert_eq!(get_file(&dir, "/missing.html"), None);
+        assert_eq!(get_file(&dir, "/list/../a b.json"), None);
+        assert_eq!(get_file(&dir, &decode("/%2e%2e/etc/passwd")), None);
+        assert_eq!(get_file(&dir, &decode("/list/..%2F..%2Fa b.json")), None);
+    }
+
+    #[test]
+    fn get_rules_reads_the_pages_of_the_manifest() {
+        let pages = Pages::new("rules");
+        let dir = pages.dir();
+        assert!(get_rules(&dir).unwrap().is_empty());
+        std::fs::write(
+            pages.0.join(MANIFEST),
+            "# pages\n[\"/old\"]\nredirect = \"/list/\"\nstatus = 301\n\n[\"/slow*\"]\ndelay = 0.5\nfile = 'a b.json'\nheader.x-kind = \"slow\"\n",
+        )
+        .unwrap();
+        let rules = get_rules(&dir).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].path, "/old");
+        assert_eq!(rules[0].redirect.as_deref(), Some("/list/"));
+        assert_eq!(rules[0].status, Some(301));
+        assert_eq!(rules[1].delay, Some(0.5));
+        assert_eq!(rules[1].file.as_deref(), Some("a b.json"));
+        assert_eq!(
+            rules[1].headers,
+            vec![("x-kind".to_string(), "slow".to_string())]
+        );
+        std::fs::write(pages.0.join(MANIFEST), "[\"/a\"]\nstatuss = 404\n").unwrap();
+        assert!(get_rules(&dir)
+            .unwrap_err()
+            .contains("did you mean `status`?"));
+        std::fs::write(pages.0.join(MANIFEST), "status = 404\n").unwrap();
+        assert!(get_rules(&dir).is_err());
+    }
+}
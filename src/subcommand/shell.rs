@@ -0,0 +1,455 @@
+use crate::select::{Css, Html, Json, Take, XPath};
+use crate::subcommand::run::MetaData;
+use crate::subcommand::test_parser::FIXTURES;
+use crate::util;
+use std::io::{BufRead, Write};
+
+/// matches shown for an expression, the rest are counted only
+const SHOWN: usize = 20;
+/// characters shown of a match
+const WIDTH: usize = 120;
+/// the locals of the skeleton parser, which a field cannot shadow
+const LOCALS: [&str; 5] = ["res", "body", "document", "value", "parsed"];
+/// the commands of the shell
+const HELP: &str = "    css <selector>    css as `scraper` takes it, `::text` or `::attr(name)` takes the text or an attribute
+    xpath <expr>      xpath of `res.xpath()`, which needs feature `xpath` of dyer
+    re <pattern>      regex on the body, the first group is taken if any
+    json <pointer>    json pointer on the body, eg. `/data/0/name`
+    keep <field>      keep the last expression as a field of the parser
+    drop <field>      drop the field kept
+    fields            list the fields kept
+    parser [name]     print the `#[dyer::parser]` taking the fields
+    help              print the commands
+    quit              leave the shell, the parser is printed if the fields changed since";
+
+// dyer shell <fixture-or-file>
+#[derive(std::fmt::Debug)]
+pub struct SubComShell {
+    // a file, or a fixture as `<parser>/<case>` or `<case>` in tests/fixtures/
+    pub target: String,
+}
+
+/// the kinds of expression tried in the shell
+#[derive(std::fmt::Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Css,
+    XPath,
+    Re,
+    Json,
+}
+
+/// an expression kept as a field of the parser
+#[derive(std::fmt::Debug, Clone)]
+struct Field {
+    name: String,
+    kind: Kind,
+    expr: String,
+}
+
+/// the response loaded into the shell
+#[derive(std::fmt::Debug)]
+struct Loaded {
+    path: String,
+    // the parser of the fixture
+    parser: Option<String>,
+    // the entities of the project the parser returns, `Entities` outside of a project
+    entities: String,
+    // the version of dyer the parser is written against
+    dyer_version: &'static str,
+    body: String,
+    html: Html,
+    // the body read as json on the first `json` command
+    json: std::cell::OnceCell<Result<Json, String>>,
+}
+
+impl SubComShell {
+    pub fn execute(&self) {
+        let path = find_response(&self.target).unwrap_or_else(|e| util::exit_with(&e));
+        let body = std::fs::read(&path)
+            .unwrap_or_else(|e| util::exit_with(&format!("failed to read {}: {}", path, e)));
+        let body = String::from_utf8_lossy(&body).to_string();
+        let parser = std::path::Path::new(&path)
+            .parent()
+            .filter(|dir| dir.parent() == Some(std::path::Path::new(FIXTURES)))
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string());
+        println!("    loaded {} {}B", path, body.len());
+        let head = std::path::Path::new(&path).with_extension("head");
+        if let Ok(head) = std::fs::read_to_string(head) {
+            let mut lines = head.lines();
+            let request = lines.next().unwrap_or_default();
+            let status = lines.next().unwrap_or_default();
+            let content_type = lines
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+                .map(|(_, value)| value.trim())
+                .unwrap_or_default();
+            println!("    {}, {} {}", request, status, content_type);
+        }
+        let mut meta = MetaData::new();
+        let entities = match meta.try_init() {
+            Ok(()) => meta.get_entities(),
+            Err(_) => "Entities".to_string(),
+        };
+        let loaded = Loaded {
+            entities,
+            dyer_version: meta.dyer_version,
+            html: Html::parse(&body),
+            json: std::cell::OnceCell::new(),
+            path,
+            parser,
+            body,
+        };
+        println!("{}", HELP);
+        repl(&loaded);
+    }
+}
+
+/// the body at `target`, a file or a fixture saved for `dyer test-parser`
+fn find_response(target: &str) -> Result<String, String> {
+    if std::path::Path::new(target).is_file() {
+        return Ok(target.to_string());
+    }
+    let (dirs, case) = match target.rsplit_once('/') {
+        Some((parser, case)) => (vec![format!("{}/{}", FIXTURES, parser)], case),
+        None => {
+            let dirs = std::fs::read_dir(FIXTURES)
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path())
+                        .filter(|path| path.is_dir())
+                        .map(|path| path.to_string_lossy().to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            (dirs, target)
+        }
+    };
+    let mut found = Vec::new();
+    for dir in dirs.iter() {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            found.extend(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file())
+                    .filter(|path| !path.to_string_lossy().ends_with(".expected.json"))
+                    .filter(|path| path.extension().is_none_or(|ext| ext != "head"))
+                    .filter(|path| path.file_stem().is_some_and(|stem| stem == case))
+                    .map(|path| path.to_string_lossy().to_string()),
+            );
+        }
+    }
+    found.sort();
+    match found.len() {
+        0 => Err(format!(
+            "`{}` is neither a file nor a case in {}/, eg. `dyer shell parse_quote/page1`",
+            target, FIXTURES
+        )),
+        1 => Ok(found.pop().unwrap()),
+        _ => Err(format!(
+            "`{}` is ambiguous, give one of {}",
+            target,
+            found.join(", ")
+        )),
+    }
+}
+
+fn repl(loaded: &Loaded) {
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut last: Option<(Kind, String)> = None;
+    let mut fields: Vec<Field> = Vec::new();
+    // the fields changed since the parser was printed
+    let mut changed = false;
+    // the name of the parser last printed
+    let mut name = String::new();
+    let pat = regex::Regex::new(r"^[a-z_][a-z0-9_]*$").unwrap();
+    loop {
+        print!(">>> ");
+        std::io::stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => {
+                println!();
+                break;
+            }
+        };
+        let line = line.trim();
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        let kind = match command {
+            "css" => Some(Kind::Css),
+            "xpath" => Some(Kind::XPath),
+            "re" => Some(Kind::Re),
+            "json" => Some(Kind::Json),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            match select(loaded, kind, arg) {
+                Ok(values) => {
+                    show(&values);
+                    last = Some((kind, arg.to_string()));
+                }
+                Err(e) => println!("    error: {}", e),
+            }
+            continue;
+        }
+        match command {
+            "" => {}
+            "keep" => match last.as_ref() {
+                None => println!("    try an expression before keeping it"),
+                Some(_) if !pat.is_match(arg) => println!(
+                    "    `{}` cannot name a field, use lowercase letters, digits and `_`",
+                    arg
+                ),
+                Some(_) if util::KEYWORDS.contains(&arg) => {
+                    println!("    `{}` is a Rust keyword and cannot name a field", arg)
+                }
+                Some(_) if LOCALS.contains(&arg) => println!(
+                    "    `{}` is used by the parser printed and cannot name a field, locals: {}",
+                    arg,
+                    LOCALS.join(", ")
+                ),
+                Some((kind, expr)) => {
+                    let field = Field {
+                        name: arg.to_string(),
+                        kind: *kind,
+                        expr: expr.clone(),
+                    };
+                    match fields.iter_mut().find(|f| f.name == arg) {
+                        Some(kept) => *kept = field,
+                        None => fields.push(field),
+                    }
+                    println!("    keep `{}` as {} `{}`", arg, command_of(*kind), expr);
+                    changed = true;
+                }
+            },
+            "drop" => match fields.iter().position(|f| f.name == arg) {
+                Some(pos) => {
+                    fields.remove(pos);
+                    changed = true;
+                }
+                None => println!("    no field named `{}`", arg),
+            },
+            "fields" => {
+                for field in fields.iter() {
+                    println!(
+                        "    {}: {} {}",
+                        field.name,
+                        command_of(field.kind),
+                        field.expr
+                    );
+                }
+            }
+            "parser" => {
+                if !arg.is_empty() {
+                    name = arg.to_string();
+                }
+                println!("{}", render_parser(loaded, &name, &fields));
+                changed = false;
+            }
+            "help" => println!("{}", HELP),
+            "quit" | "exit" => break,
+            _ => println!(
+                "    unknown command `{}`, `help` lists the commands",
+                command
+            ),
+        }
+    }
+    if changed && !fields.is_empty() {
+        println!("{}", render_parser(loaded, &name, &fields));
+    }
+}
+
+fn command_of(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Css => "css",
+        Kind::XPath => "xpath",
+        Kind::Re => "re",
+        Kind::Json => "json",
+    }
+}
+
+/// the matches of the expression, each taken as the generated parser takes it
+fn select(loaded: &Loaded, kind: Kind, expr: &str) -> Result<Vec<String>, String> {
+    if expr.is_empty() {
+        return Err(format!("`{}` takes an expression", command_of(kind)));
+    }
+    match kind {
+        Kind::Css => Ok(Css::parse(expr)?.select(&loaded.html)),
+        Kind::XPath => Ok(XPath::parse(expr)?.select(&loaded.html)),
+        Kind::Re => {
+            let re = regex::Regex::new(expr).map_err(|e| e.to_string())?;
+            Ok(match re.captures_len() {
+                1 => re
+                    .find_iter(&loaded.body)
+                    .map(|m| m.as_str().to_string())
+                    .collect(),
+                _ => re
+                    .captures_iter(&loaded.body)
+                    .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string()))
+                    .collect(),
+            })
+        }
+        Kind::Json => {
+            let json = loaded
+                .json
+                .get_or_init(|| Json::parse(&loaded.body))
+                .as_ref()
+                .map_err(|e| format!("the body is not json: {}", e))?;
+            Ok(json
+                .pointer(expr)?
+                .map(|value| value.to_string())
+                .into_iter()
+                .collect())
+        }
+    }
+}
+
+fn show(values: &[String]) {
+    for (i, value) in values.iter().take(SHOWN).enumerate() {
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        let clipped = value.chars().take(WIDTH).collect::<String>();
+        match clipped.len() < value.len() {
+            true => println!("    [{}] {}...", i, clipped),
+            false => println!("    [{}] {}", i, clipped),
+        }
+    }
+    if values.len() > SHOWN {
+        println!("    ... {} more", values.len() - SHOWN);
+    }
+    println!("    {} match(es)", values.len());
+}
+
+/// the literal of `s` in rust, raw if it has quotes or backslashes
+fn literal(s: &str) -> String {
+    if !(s.contains('"') || s.contains('\\')) {
+        return format!("{:?}", s);
+    }
+    let mut hashes = String::from("#");
+    while s.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+    format!("r{}\"{}\"{}", hashes, s, hashes)
+}
+
+/// the skeleton `#[dyer::parser]` taking the fields as the shell shows them
+fn render_parser(loaded: &Loaded, name: &str, fields: &[Field]) -> String {
+    let name = match name {
+        "" => loaded.parser.as_deref().unwrap_or("parse_page"),
+        name => name,
+    };
+    let uses = |kind: Kind| fields.iter().any(|f| f.kind == kind);
+    let mut requires = Vec::new();
+    if uses(Kind::Css) {
+        requires.push("`scraper`");
+    }
+    if uses(Kind::Re) {
+        requires.push("`regex`");
+    }
+    if uses(Kind::Json) {
+        requires.push("`serde_json`");
+    }
+    let mut requirement = String::new();
+    if !requires.is_empty() {
+        requirement.push_str(&format!(
+            " requires {} in [dependencies]",
+            match requires.split_last() {
+                Some((last, [])) => last.to_string(),
+                Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+                None => String::new(),
+            }
+        ));
+    }
+    if uses(Kind::XPath) {
+        requirement.push_str(match requires.is_empty() {
+            true => " requires feature `xpath` of dyer",
+            false => ", along with feature `xpath` of dyer",
+        });
+    }
+    let mut buf = format!(
+        "/* generated by `dyer shell` from {},{}\n * put the fields into an entity of src/entity.rs and return it in `parsed`\n */\n",
+        loaded.path,
+        match requirement.is_empty() {
+            true => String::new(),
+            false => format!("\n *{}", requirement),
+        }
+    );
+    buf.push_str(&format!(
+        "#[dyer::parser]\npub fn {}({}res: Response) -> Parsed<{}> {{\n    let parsed = Parsed::new();\n",
+        name,
+        if uses(Kind::XPath) { "mut " } else { "" },
+        loaded.entities
+    ));
+    let bytes = util::get_version_rule(loaded.dyer_version, "res_bytes");
+    if uses(Kind::Css) || uses(Kind::Re) {
+        buf.push_str(&format!(
+            "    let body = String::from_utf8_lossy(&{}).to_string();\n",
+            bytes
+        ));
+    }
+    if uses(Kind::Css) {
+        buf.push_str("    let document = scraper::Html::parse_document(&body);\n");
+    }
+    if uses(Kind::Json) {
+        buf.push_str(&format!(
+            "    let value: serde_json::Value =\n        serde_json::from_slice(&{}).unwrap_or_default();\n",
+            bytes
+        ));
+    }
+    for field in fields.iter() {
+        buf.push_str(&render_field(field));
+    }
+    buf.push_str("    parsed\n}");
+    buf
+}
+
+fn render_field(field: &Field) -> String {
+    let mut buf = format!("    // {} {}\n", command_of(field.kind), field.expr);
+    match field.kind {
+        Kind::Css => {
+            // a valid selector is kept only
+            let css = Css::parse(&field.expr).unwrap();
+            let take = match css.take {
+                Take::Html => ".map(|e| e.html())".to_string(),
+                Take::Text => ".map(|e| e.text().collect::<String>().trim().to_string())".to_string(),
+                Take::Attr(attr) => format!(
+                    ".filter_map(|e| e.value().attr({}).map(|v| v.to_string()))",
+                    literal(&attr)
+                ),
+            };
+            buf.push_str(&format!(
+                "    let {} = document\n        .select(&scraper::Selector::parse({}).unwrap())\n        {}\n        .collect::<Vec<String>>();\n",
+                field.name,
+                literal(&css.selector),
+                take
+            ));
+        }
+        Kind::XPath => buf.push_str(&format!(
+            "    let {} = res\n        .xpath({})\n        .iter()\n        .map(|node| node.get_content())\n        .collect::<Vec<String>>();\n",
+            field.name,
+            literal(&field.expr)
+        )),
+        Kind::Re => {
+            let groups = regex::Regex::new(&field.expr).unwrap().captures_len();
+            let take = match groups {
+                1 => ".find_iter(&body)\n        .map(|m| m.as_str().to_string())",
+                _ => ".captures_iter(&body)\n        .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string()))",
+            };
+            buf.push_str(&format!(
+                "    let {} = regex::Regex::new({})\n        .unwrap()\n        {}\n        .collect::<Vec<String>>();\n",
+                field.name,
+                literal(&field.expr),
+                take
+            ));
+        }
+        Kind::Json => buf.push_str(&format!(
+            "    let {} = value.pointer({}).cloned();\n",
+            field.name,
+            literal(&field.expr)
+        )),
+    }
+    buf
+}
@@ -0,0 +1,260 @@
+use crate::subcommand::run::{self, MetaData};
+use crate::template;
+use crate::util;
+use std::collections::HashMap;
+
+/// the saved responses of parser `<name>` are in `tests/fixtures/<name>/`
+pub(crate) const FIXTURES: &str = "tests/fixtures";
+/// the test target generated to run the parsers against the fixtures
+const HARNESS: &str = "dyer_parsers";
+
+/// the harness, each saved response is fed into the parser and the serialised `Parsed` is compared
+/// with `<case>.expected.json` next to it, `<case>.head` saved by `dyer fetch` gives its uri, status
+/// and headers
+const HARNESS_TEMPLATE: &str = r#"//! generated by `dyer test-parser` from the fixtures in `tests/fixtures/`, changes are overwritten
+extern crate <+package_name+>;
+
+use <+package_name+>::parser;
+
+/// the helpers of the tests, in a module so that no parser shadows them
+mod harness {
+    use dyer::*;
+    use <+package_name+>::entity::*;
+
+    pub fn check(name: &str, parser: fn(Response) -> Parsed<<+entities+>>) {
+        let update = std::env::var("DYER_UPDATE").is_ok();
+        let show = std::env::var("DYER_SHOW").ok();
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("<+fixtures+>")
+            .join(name);
+        let mut paths = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| !path.to_string_lossy().ends_with(".expected.json"))
+            .filter(|path| path.extension().map_or(true, |ext| ext != "head"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        let mut failures = Vec::new();
+        for path in paths {
+            let case = path.file_stem().unwrap().to_string_lossy().to_string();
+            let body = std::fs::read(&path).unwrap();
+            let actual = serialize(&parser(load(&path, body)));
+            if let Some(show) = show.as_ref() {
+                if *show == case {
+                    println!("{}", actual);
+                }
+                continue;
+            }
+            let snapshot = dir.join(format!("{}.expected.json", case));
+            if update {
+                std::fs::write(&snapshot, &actual).unwrap();
+                println!("updated {}", snapshot.display());
+                continue;
+            }
+            match std::fs::read_to_string(&snapshot) {
+                Ok(expected) if expected.trim_end() == actual.trim_end() => {}
+                Ok(expected) => failures.push(format!("{}:\n{}", path.display(), diff(&expected, &actual))),
+                Err(_) => failures.push(format!(
+                    "{}: no snapshot, run `dyer test-parser {} --update` to create it",
+                    path.display(),
+                    name
+                )),
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "parser `{}` does not match its snapshots\n{}",
+            name,
+            failures.join("\n")
+        );
+    }
+
+    /// the response of the body, with the request line, the status line and the headers in `<case>.head`
+    fn load(path: &std::path::Path, body: Vec<u8>) -> Response {
+        let head = match std::fs::read_to_string(path.with_extension("head")) {
+            Ok(head) => head,
+            Err(_) => return Response::new(body),
+        };
+        let mut lines = head.lines();
+        let uri = lines.next().and_then(|line| line.split_whitespace().nth(1)).unwrap_or("/");
+        let status = lines
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|status| status.parse::<u16>().ok())
+            .unwrap_or(200);
+        let mut builder = Response::builder().status(status);
+        for line in lines {
+            if let Some((name, value)) = line.split_once(':') {
+                builder = builder.header(name.trim(), value.trim());
+            }
+        }
+        let mut res = builder.body(body);
+        res.metas.info.from = uri.parse().unwrap();
+        res
+    }
+
+    fn serialize(parsed: &Parsed<<+entities+>>) -> String {
+        let tasks = parsed
+            .task
+            .iter()
+            .map(|task| serde_json::json!({ "method": task.method().as_str(), "uri": task.uri().to_string() }))
+            .collect::<Vec<_>>();
+        let value = serde_json::json!({
+            "entities": parsed.entities,
+            "tasks": tasks,
+            "requests": parsed.req.len(),
+            "affixes": parsed.affix.len(),
+            "errs": parsed.errs.len(),
+        });
+        serde_json::to_string_pretty(&value).unwrap() + "\n"
+    }
+
+    /// the lines removed from the expected(-) and added to the actual(+)
+    fn diff(expected: &str, actual: &str) -> String {
+        let a = expected.lines().collect::<Vec<_>>();
+        let b = actual.lines().collect::<Vec<_>>();
+        let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        let mut lines = Vec::new();
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                i += 1;
+                j += 1;
+            } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(format!("    -{}", a[i]));
+                i += 1;
+            } else {
+                lines.push(format!("    +{}", b[j]));
+                j += 1;
+            }
+        }
+        lines.join("\n")
+    }
+}
+<+tests+>"#;
+
+// dyer test-parser [name] [--update]
+#[derive(std::fmt::Debug)]
+pub struct SubComTestParser {
+    // the parser to test, all parsers with fixtures if `None`
+    pub name: Option<String>,
+    // overwrite the snapshots with the current output of parsers
+    pub update: bool,
+    // print the output of the parser on the case instead of comparing with the snapshots
+    pub show: Option<String>,
+    // options of `cargo test`
+    pub options: Vec<String>,
+}
+
+impl SubComTestParser {
+    pub fn execute(&self) {
+        let mut meta = MetaData::new();
+        meta.init();
+        let mut parsers = meta.get_parsers();
+        parsers.sort();
+        if let Some(name) = self.name.as_ref() {
+            if !parsers.contains(name) {
+                util::exit_with(&format!(
+                    "no `#[dyer::parser]` named `{}` is found in src/parser.rs, parsers: {}",
+                    name,
+                    parsers.join(", ")
+                ));
+            }
+            if !std::path::Path::new(&format!("{}/{}", FIXTURES, name)).is_dir() {
+                util::exit_with(&format!(
+                    "no fixtures of `{}`, save the bodies of responses in {}/{}/ such as `page.html`",
+                    name, FIXTURES, name
+                ));
+            }
+        }
+        if let Ok(entries) = std::fs::read_dir(FIXTURES) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                let dir = entry.file_name().to_string_lossy().to_string();
+                if !parsers.contains(&dir) {
+                    println!(
+                        "    skip {}/{}: no parser named `{}` is found",
+                        FIXTURES, dir, dir
+                    );
+                }
+            }
+        }
+        let tested = parsers
+            .into_iter()
+            .filter(|p| std::path::Path::new(&format!("{}/{}", FIXTURES, p)).is_dir())
+            .collect::<Vec<String>>();
+        if tested.is_empty() {
+            util::exit_with(&format!(
+                "no fixtures are found, save the bodies of responses parsed by parser `<name>` in {}/<name>/",
+                FIXTURES
+            ));
+        }
+
+        self.ensure_serde_json();
+        run::ensure_main(None);
+        // the tests are named after their parsers, so that `-- <name> --exact` runs one
+        let tests = tested
+            .iter()
+            .map(|p| {
+                format!(
+                    "\n#[test]\nfn {}() {{\n    harness::check(\"{}\", parser::{});\n}}\n",
+                    p, p, p
+                )
+            })
+            .collect::<String>();
+        let mut vars = HashMap::new();
+        vars.insert("package_name".to_string(), meta.package_name.clone());
+        vars.insert("entities".to_string(), meta.get_entities());
+        vars.insert("fixtures".to_string(), FIXTURES.to_string());
+        vars.insert("tests".to_string(), tests);
+        std::fs::create_dir_all("tests").unwrap();
+        std::fs::write(
+            format!("tests/{}.rs", HARNESS),
+            template::render_str(HARNESS_TEMPLATE, &vars),
+        )
+        .unwrap();
+
+        if self.update {
+            std::env::set_var("DYER_UPDATE", "1");
+        }
+        if let Some(case) = self.show.as_ref() {
+            std::env::set_var("DYER_SHOW", case);
+        }
+        let mut args = vec!["test", "--test", HARNESS];
+        args.extend(self.options.iter().map(|op| op.as_str()));
+        if let Some(name) = self.name.as_ref() {
+            args.extend(["--", name.as_str(), "--exact"].iter());
+            if self.show.is_some() {
+                args.push("--nocapture");
+            }
+        }
+        if !util::run_command("cargo", args) {
+            std::process::exit(1);
+        }
+    }
+
+    /// the harness serialises with serde_json, added to the dependencies if missing
+    fn ensure_serde_json(&self) {
+        let manifest = std::fs::read_to_string("Cargo.toml").unwrap();
+        let (merged, added) =
+            util::merge_manifest(&manifest, "[dependencies]\nserde_json = \"1.0\"\n", &[]);
+        let in_dev = util::get_manifest_table(&manifest, "dev-dependencies")
+            .iter()
+            .any(|(key, _)| key == "serde_json");
+        if added.is_empty() || in_dev {
+            return;
+        }
+        std::fs::write("Cargo.toml", merged).unwrap();
+        println!("    edit  Cargo.toml: serde_json added to serialise the parsed");
+    }
+}
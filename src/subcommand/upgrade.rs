@@ -0,0 +1,234 @@
+use crate::subcommand::run::{self, MetaData};
+use crate::util;
+
+/// source rewrites from a version of dyer to the next one: from, to, pattern and replacement
+const REWRITES: [(&str, &str, &str, &str); 2] = [
+    // the marker of `MiddleWare` and `PipeLine` is `impl Into<String>` since 3.3
+    (
+        "3.2",
+        "3.3",
+        r#"\.build\(\s*("[^"]*")\s*\.into\(\)\s*\)"#,
+        ".build($1)",
+    ),
+    // tokio 1 renamed `delay_for` to `sleep`, a path to it only lest another `delay_for` is renamed
+    (
+        "3.2",
+        "3.3",
+        r"\b((?:tokio::)?time::)delay_for\b",
+        "${1}sleep",
+    ),
+];
+
+/// usages that cannot be rewritten safely: from, to, pattern and what to do about them
+const ATTENTIONS: [(&str, &str, &str, &str); 5] = [
+    (
+        "3.2",
+        "3.3",
+        r"\bClient::exec_one\b",
+        "`Client::exec_one` is removed, request with `req.get_client().request(req)` instead",
+    ),
+    (
+        "3.2",
+        "3.3",
+        r"\.body_fn\(",
+        "`Request::body_fn` takes and returns `Self` now, use `body_fn_mut` to set it in place",
+    ),
+    (
+        "3.2",
+        "3.3",
+        r"\b(Request|Affix|Task)::from_parts\(",
+        "`from_parts` takes the proxy as its last argument now",
+    ),
+    (
+        "3.2",
+        "3.3",
+        r"\btokio::(runtime|stream)\b",
+        "tokio is upgraded from 0.2 to 1, whose runtime and stream API changed",
+    ),
+    (
+        "3.2",
+        "3.3",
+        r"(^|[^:\w])delay_for\b",
+        "`delay_for` of tokio is renamed `sleep`, rename it if it is imported from `tokio::time`",
+    ),
+];
+
+// dyer upgrade [--to <version>]
+#[derive(std::fmt::Debug)]
+pub struct SubComUpgrade {
+    // the version to upgrade to, `util::DYER_VERSION` as default
+    pub to: Option<String>,
+}
+
+impl SubComUpgrade {
+    pub fn execute(&self) {
+        let manifest = std::fs::read_to_string("Cargo.toml").unwrap_or_else(|_| {
+            util::exit_with("current directory must contain `Cargo.toml` file")
+        });
+        let current = util::get_dyer_dependency(&manifest).unwrap_or_else(|| {
+            util::exit_with("the dependency of dyer cannot be found in Cargo.toml")
+        });
+        let from = util::resolve_dyer_version(&current).unwrap_or_else(|e| util::exit_with(&e));
+        let to_raw = self.to.as_deref().unwrap_or(util::DYER_VERSION);
+        let to = util::resolve_dyer_version(to_raw).unwrap_or_else(|e| util::exit_with(&e));
+        // the supported versions are listed from the latest
+        let position = |v: &str| util::DYER_VERSIONS.iter().position(|x| *x == v).unwrap();
+        if position(to) > position(from) {
+            util::exit_with(&format!(
+                "dyer {} is older than dyer {} the project depends on, downgrading is not supported",
+                to_raw, current
+            ));
+        }
+        // the versions passed through, each step applies its own rewrites
+        let steps = util::DYER_VERSIONS[position(to)..=position(from)]
+            .iter()
+            .rev()
+            .copied()
+            .collect::<Vec<&str>>();
+        let mut attentions = Vec::new();
+
+        println!("upgrading dyer {} to {} ...", current, to_raw);
+        // the files are written only once the main is generated from them
+        let mut edits = Vec::new();
+        let (buf, notes) = upgrade_manifest(&manifest, from, to, to_raw);
+        attentions.extend(notes);
+        if buf != manifest {
+            edits.push((
+                "./Cargo.toml".to_string(),
+                buf,
+                "    edit  Cargo.toml".to_string(),
+            ));
+        }
+
+        let mut meta = MetaData::new();
+        meta.init();
+        for path in meta.get_paths() {
+            let mut buf = std::fs::read_to_string(&path).unwrap();
+            let mut count = 0;
+            for step in steps.windows(2) {
+                for (_, _, pat, rep) in REWRITES.iter().filter(|r| r.0 == step[0] && r.1 == step[1])
+                {
+                    let pat = regex::Regex::new(pat).unwrap();
+                    count += pat.find_iter(&buf).count();
+                    buf = pat.replace_all(&buf, *rep).to_string();
+                }
+                for (_, _, pat, msg) in ATTENTIONS
+                    .iter()
+                    .filter(|a| a.0 == step[0] && a.1 == step[1])
+                {
+                    let pat = regex::Regex::new(pat).unwrap();
+                    for (i, line) in buf.lines().enumerate() {
+                        if pat.is_match(line) {
+                            attentions.push(format!("{}:{}: {}", path, i + 1, msg));
+                        }
+                    }
+                }
+            }
+            if count > 0 {
+                let note = format!("    edit  {}: {} rewrite(s)", path, count);
+                edits.push((path, buf, note));
+            }
+        }
+
+        // the modules changed, so does the generated main
+        let main_str = compose_upgraded(&edits).unwrap_or_else(|e| {
+            util::exit_with(&format!(
+                "the project is left as it is, the main cannot be generated after upgrading: {}",
+                e
+            ))
+        });
+        for (path, buf, note) in edits.iter() {
+            std::fs::write(path, buf).unwrap();
+            println!("{}", note);
+        }
+        let mut meta = MetaData::new();
+        meta.init();
+        meta.write_main(&main_str);
+        println!("    regenerate src/bin/{}.rs", meta.bin_name);
+
+        if attentions.is_empty() {
+            println!("project is upgraded to dyer {} successfully!", to_raw);
+            return;
+        }
+        println!(
+            "project is upgraded to dyer {}, the following need manual attention:",
+            to_raw
+        );
+        for attention in attentions.iter() {
+            println!("    {}", attention);
+        }
+    }
+}
+
+/// the main generated from the project with `edits` applied, composed in a copy of the project
+fn compose_upgraded(edits: &[(String, String, String)]) -> Result<String, String> {
+    let meta = MetaData::new();
+    let dir = std::env::temp_dir().join(format!("dyer-upgrade-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut paths = meta.get_inputs();
+    paths.push(run::FINGERPRINT.to_string());
+    paths.push(meta.get_main_path().trim_start_matches("./").to_string());
+    let staged = paths.iter().try_for_each(|path| {
+        let target = dir.join(path);
+        std::fs::create_dir_all(target.parent().unwrap())?;
+        let edited = edits
+            .iter()
+            .find(|(p, _, _)| p.trim_start_matches("./") == path);
+        match edited {
+            Some((_, buf, _)) => std::fs::write(target, buf),
+            None if std::path::Path::new(path).exists() => std::fs::copy(path, target).map(|_| ()),
+            None => Ok(()),
+        }
+    });
+    let composed = staged
+        .map_err(|e| format!("failed to copy the project into {}: {}", dir.display(), e))
+        .and_then(|_| {
+            let mut staged = MetaData::new();
+            staged.base_dir = format!("{}/", dir.display());
+            staged.try_init()?;
+            staged.compose_main(false)
+        });
+    let _ = std::fs::remove_dir_all(&dir);
+    composed
+}
+
+/// bump the dependencies of dyer and tokio, with the notes of what is left as it is
+fn upgrade_manifest(manifest: &str, from: &str, to: &str, to_raw: &str) -> (String, Vec<String>) {
+    let header = regex::Regex::new(r"^\s*\[(?P<section>[^\]]*)\]").unwrap();
+    let dyer = regex::Regex::new(
+        r#"^(?P<head>\s*dyer\s*=\s*(\{.*?\bversion\s*=\s*)?["'])[^"']+(?P<tail>["'].*)$"#,
+    )
+    .unwrap();
+    let tokio = regex::Regex::new(r"^\s*tokio\s*=\s*(?P<value>.*?)\s*$").unwrap();
+    let old_tokio = util::get_version_rule(from, "tokio");
+    let new_tokio = util::get_version_rule(to, "tokio");
+    let mut notes = Vec::new();
+    let mut in_section = false;
+    let mut lines = Vec::new();
+    for line in manifest.lines() {
+        if let Some(cap) = header.captures(line) {
+            in_section = cap["section"].trim() == "dependencies";
+        } else if in_section {
+            if let Some(cap) = dyer.captures(line) {
+                lines.push(format!("{}{}{}", &cap["head"], to_raw, &cap["tail"]));
+                continue;
+            }
+            if let Some(cap) = tokio.captures(line) {
+                if &cap["value"] == old_tokio {
+                    lines.push(format!("tokio = {}", new_tokio));
+                    continue;
+                }
+                if &cap["value"] != new_tokio {
+                    notes.push(format!(
+                        "Cargo.toml: tokio is kept as it is, dyer {} works with tokio = {}",
+                        to, new_tokio
+                    ));
+                }
+            }
+        }
+        lines.push(line.to_string());
+    }
+    let mut buf = lines.join("\n");
+    buf.push('\n');
+    (buf, notes)
+}
@@ -0,0 +1,140 @@
+use crate::subcommand::run::{self, MetaData};
+use crate::util;
+
+/// the cargo commands that can be watched
+const COMMANDS: [&str; 3] = ["check", "test", "run"];
+
+/// interval of polling the inputs
+const POLL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// the inputs have to stay unchanged for this long before the command is re-run, so that a burst of
+/// saves by editor or `git checkout` triggers only one
+const DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(300);
+
+// dyer watch [check|test|run] [options of the cargo command]
+#[derive(std::fmt::Debug)]
+pub struct SubComWatch {
+    // the cargo command re-run on changes, `check` as default
+    pub command: String,
+    pub options: Vec<String>,
+}
+
+impl SubComWatch {
+    pub fn execute(&self) {
+        if !COMMANDS.contains(&self.command.as_str()) {
+            util::exit_with(&format!(
+                "cannot watch `{}`, expected one of: {}",
+                self.command,
+                COMMANDS.join(", ")
+            ));
+        }
+        util::forward_signals();
+        let mut snapshot = get_snapshot();
+        let mut child = self.start();
+        loop {
+            std::thread::sleep(POLL);
+            if let Some(c) = child.as_mut() {
+                if let Ok(Some(status)) = c.try_wait() {
+                    util::set_child(None);
+                    child = None;
+                    if !util::is_interrupted() {
+                        println!(
+                            "    `cargo {}` exited with {}, waiting for changes ...",
+                            self.command, status
+                        );
+                    }
+                }
+            }
+            if util::is_interrupted() {
+                if let Some(c) = child.as_mut() {
+                    let _ = c.wait();
+                }
+                return;
+            }
+            let mut current = get_snapshot();
+            if current == snapshot {
+                continue;
+            }
+            loop {
+                std::thread::sleep(DEBOUNCE);
+                let next = get_snapshot();
+                if next == current {
+                    break;
+                }
+                current = next;
+            }
+            snapshot = current;
+            if let Some(mut c) = child.take() {
+                println!(
+                    "    changes detected, stopping `cargo {}` ...",
+                    self.command
+                );
+                util::terminate(&mut c);
+                util::set_child(None);
+            }
+            // the signal may be meant for dyer-cli while the child is stopped
+            if util::is_interrupted() {
+                return;
+            }
+            child = self.start();
+        }
+    }
+
+    /// regenerate the main if needed and spawn the cargo command, `None` if the main cannot be
+    /// generated till the next change
+    fn start(&self) -> Option<std::process::Child> {
+        let mut meta = MetaData::new();
+        // a project in the middle of an edit is reported till the next change
+        match run::try_ensure_main(&mut meta) {
+            // the main stays the same unless annotated items change
+            Ok(Some(old)) => {
+                let main_str = std::fs::read_to_string(meta.get_main_path()).unwrap_or_default();
+                // the log level set by options is not a change of the main
+                if run::hash_generated(&old) != run::hash_generated(&main_str) {
+                    println!(
+                        "    regenerate {}",
+                        meta.get_main_path().trim_start_matches("./")
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                println!("    waiting for changes ...");
+                return None;
+            }
+        }
+        let mut args = vec![self.command.as_str()];
+        for op in self.options.iter() {
+            if self.command == "run"
+                && ["--off", "--error", "--warn", "--info", "--debug", "--trace"]
+                    .contains(&op.as_str())
+            {
+                util::change_log_level(op);
+                continue;
+            }
+            args.push(op.as_str());
+        }
+        println!("    running `cargo {}` ...", args.join(" "));
+        let child = std::process::Command::new("cargo")
+            .args(args)
+            .spawn()
+            .unwrap_or_else(|e| util::exit_with(&format!("failed to run `cargo`: {}", e)));
+        util::set_child(Some(&child));
+        Some(child)
+    }
+}
+
+/// the modified time and size of every input of the generated main, the generated main excluded
+fn get_snapshot() -> Vec<(String, Option<std::time::SystemTime>, u64)> {
+    MetaData::new()
+        .get_inputs()
+        .into_iter()
+        .map(|path| {
+            let meta = std::fs::metadata(&path).ok();
+            let modified = meta.as_ref().and_then(|m| m.modified().ok());
+            let len = meta.map(|m| m.len()).unwrap_or(0);
+            (path, modified, len)
+        })
+        .collect()
+}
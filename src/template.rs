@@ -0,0 +1,230 @@
+//! user-defined project templates used by `dyer new --template <path|name>`
+//!
+//! a template is a directory whose files are copied into the new project with
+//! placeholders `<+name+>`, `<+crate_name+>`, `<+log_level+>`, `<+author+>`, `<+dyer_version+>`
+//! and any `--var key=value` replaced both in file contents and file names.
+//! An optional `template.cfg` at the root of the template is not copied, it
+//! describes the template and the files rendered only under some condition:
+//! ```bash
+//! ## shown by `dyer new --list-templates`
+//! description: crawler with sqlx pipeline and proxy middleware
+//!
+//! ## `when.<path>: <var>` render the file or directory only when `var` is set
+//! ## a leading `!` renders it only when `var` is not set
+//! when.src/proxy.rs: proxy
+//! when.migrations: !no_database
+//! ```
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// the manifest of template, excluded from rendering
+const MANIFEST: &str = "template.cfg";
+
+#[derive(Debug)]
+pub(crate) struct Template {
+    root: PathBuf,
+    pub description: String,
+    conditions: Vec<(String, String)>,
+}
+
+impl Template {
+    /// load the template located at `root`
+    pub fn open(root: PathBuf) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!("template {} is not a directory", root.display()));
+        }
+        let mut template = Template {
+            root,
+            description: String::new(),
+            conditions: Vec::new(),
+        };
+        let manifest = template.root.join(MANIFEST);
+        if manifest.exists() {
+            let buf = std::fs::read_to_string(&manifest)
+                .map_err(|e| format!("failed to read {}: {}", manifest.display(), e))?;
+            for line in buf.lines().map(|l| l.trim()) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (key, value) = match line.split_once(':') {
+                    Some((k, v)) => (k.trim(), v.trim().trim_end_matches(',').trim()),
+                    None => return Err(format!("invalid line in {}: {}", MANIFEST, line)),
+                };
+                if key == "description" {
+                    template.description = value.to_string();
+                } else if let Some(path) = key.strip_prefix("when.") {
+                    let path = path.trim_matches('/').to_string();
+                    template.conditions.push((path, value.to_string()));
+                } else {
+                    return Err(format!("unknown key `{}` in {}", key, MANIFEST));
+                }
+            }
+        }
+        Ok(template)
+    }
+
+    /// resolve the template either as a path or as the name of an installed template
+    pub fn find(name: &str) -> Result<Self, String> {
+        let path = Path::new(name);
+        if path.is_dir() {
+            return Self::open(path.to_path_buf());
+        }
+        match get_templates_dir() {
+            Some(dir) if dir.join(name).is_dir() => Self::open(dir.join(name)),
+            Some(dir) => Err(format!(
+                "template `{}` is neither a directory nor installed in {}, use `dyer new --list-templates` to see installed ones",
+                name,
+                dir.display()
+            )),
+            None => Err(format!("template `{}` cannot be found", name)),
+        }
+    }
+
+    /// whether the file or directory relative to the template root is rendered
+    fn is_enabled(&self, relative: &str, vars: &HashMap<String, String>) -> bool {
+        self.conditions.iter().all(|(path, cond)| {
+            if relative != path && !relative.starts_with(&format!("{}/", path)) {
+                return true;
+            }
+            match cond.strip_prefix('!') {
+                Some(var) => !is_set(vars, var.trim()),
+                None => is_set(vars, cond),
+            }
+        })
+    }
+
+    /// render the whole template into directory `target`
+    pub fn render(&self, target: &str, vars: &HashMap<String, String>) -> Result<(), String> {
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let entries = std::fs::read_dir(&dir)
+                .map_err(|e| format!("failed to read {}: {}", dir.display(), e))?;
+            for entry in entries {
+                let path = entry.map_err(|e| e.to_string())?.path();
+                let relative = path
+                    .strip_prefix(&self.root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                if relative == MANIFEST || !self.is_enabled(&relative, vars) {
+                    continue;
+                }
+                let dest = Path::new(target).join(render_str(&relative, vars));
+                if path.is_dir() {
+                    std::fs::create_dir_all(&dest)
+                        .map_err(|e| format!("failed to create {}: {}", dest.display(), e))?;
+                    dirs.push(path);
+                    continue;
+                }
+                if let Some(parent) = dest.parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+                }
+                let raw = std::fs::read(&path)
+                    .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+                // files that are not text such as images are copied as it is
+                let buf = match String::from_utf8(raw) {
+                    Ok(text) => render_str(&text, vars).into_bytes(),
+                    Err(e) => e.into_bytes(),
+                };
+                std::fs::write(&dest, buf)
+                    .map_err(|e| format!("failed to write {}: {}", dest.display(), e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the registry of installed templates, `$XDG_CONFIG_HOME/dyer/templates` or `~/.config/dyer/templates`
+pub(crate) fn get_templates_dir() -> Option<PathBuf> {
+    let config = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var("HOME")
+                .or_else(|_| std::env::var("USERPROFILE"))
+                .ok()?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+    Some(config.join("dyer").join("templates"))
+}
+
+/// the name and description of all installed templates
+pub(crate) fn list_templates() -> Vec<(String, String)> {
+    let dir = match get_templates_dir() {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+    let mut templates = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let description = Template::open(entry.path())
+                        .map(|t| t.description)
+                        .unwrap_or_else(|e| format!("<broken: {}>", e));
+                    (name, description)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    templates.sort();
+    templates
+}
+
+/// replace every `<+key+>` in `buf` with its value in one pass from left to right, so that a value
+/// containing a placeholder is kept as it is, unknown placeholders are left in place
+pub(crate) fn render_str(buf: &str, vars: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(buf.len());
+    let mut rest = buf;
+    while let Some(start) = rest.find("<+") {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest[2..]
+            .find("+>")
+            .and_then(|end| vars.get(&rest[2..2 + end]).map(|value| (value, end + 4)));
+        match value {
+            Some((value, len)) => {
+                rendered.push_str(value);
+                rest = &rest[len..];
+            }
+            None => {
+                rendered.push_str("<+");
+                rest = &rest[2..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn is_set(vars: &HashMap<String, String>, var: &str) -> bool {
+    match vars.get(var) {
+        Some(value) => !["", "false", "0", "no"].contains(&value.as_str()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_str_replaces_placeholders_once() {
+        let vars = [("name", "<+author+>"), ("author", "me"), ("empty", "")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<String, String>>();
+        assert_eq!(
+            render_str("<+name+> by <+author+>", &vars),
+            "<+author+> by me"
+        );
+        assert_eq!(
+            render_str("a<+empty+>b <+unknown+>", &vars),
+            "ab <+unknown+>"
+        );
+        assert_eq!(render_str("<+<+author+>+> <+", &vars), "<+me+> <+");
+    }
+}
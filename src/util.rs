@@ -24,6 +24,103 @@ impl FromStr for LogLevel {
     }
 }
 
+/// the version of `dyer` the scaffold is written against
+pub(crate) const DYER_VERSION: &str = "3.3";
+
+/// the versions of `dyer` whose code can be generated, `dyer new --dyer-version <x.y>`
+pub(crate) const DYER_VERSIONS: [&str; 2] = ["3.3", "3.2"];
+
+/// the supported version that `version` such as `3.2` or `^3.2.1` belongs to
+pub(crate) fn resolve_dyer_version(version: &str) -> Result<&'static str, String> {
+    let version = version.trim().trim_start_matches(['^', '~', '=']);
+    DYER_VERSIONS
+        .iter()
+        .find(|v| version == **v || version.starts_with(&format!("{}.", v)))
+        .copied()
+        .ok_or_else(|| {
+            format!(
+                "dyer {} is not supported, supported versions: {}",
+                version,
+                DYER_VERSIONS.join(", ")
+            )
+        })
+}
+
+/// the supported version of `dyer` in `[dependencies]` of manifest, [`DYER_VERSION`] if not found
+pub(crate) fn get_dyer_version(manifest: &str) -> &'static str {
+    get_dyer_dependency(manifest)
+        .and_then(|version| resolve_dyer_version(&version).ok())
+        .unwrap_or(DYER_VERSION)
+}
+
+/// the version requirement of `dyer` as written in `[dependencies]` of manifest
+pub(crate) fn get_dyer_dependency(manifest: &str) -> Option<String> {
+    let header = regex::Regex::new(r"^\s*\[(?P<section>[^\]]*)\]").unwrap();
+    let dependency =
+        regex::Regex::new(r#"^\s*dyer\s*=\s*(\{.*?\bversion\s*=\s*)?["'](?P<version>[^"']+)["']"#)
+            .unwrap();
+    let mut in_section = false;
+    for line in manifest.lines() {
+        if let Some(cap) = header.captures(line) {
+            in_section = cap["section"].trim() == "dependencies";
+        } else if in_section {
+            if let Some(cap) = dependency.captures(line) {
+                return Some(cap["version"].to_string());
+            }
+        }
+    }
+    None
+}
+
+/// the code generated differently between versions of `dyer`
+pub(crate) fn get_version_rule(version: &str, rule: &str) -> &'static str {
+    match (version, rule) {
+        ("3.2", "tokio") => {
+            r#"{ version = "0.2", features = ["rt-threaded", "macros", "signal"] }"#
+        }
+        (_, "tokio") => r#"{ version = "1.20", features = ["rt-multi-thread", "signal"]}"#,
+        // the marker of `MiddleWare`, `PipeLine` and `Task` is `String` before 3.3
+        ("3.2", "marker") => r#""<+marker+>".into()"#,
+        (_, "marker") => r#""<+marker+>""#,
+        // the bytes of the body of `res`, `Body::bytes` is added in 3.3
+        ("3.2", "res_bytes") => {
+            "res.body().iter().flat_map(|chunk| chunk.iter().copied()).collect::<Vec<u8>>()"
+        }
+        (_, "res_bytes") => "res.body().bytes()",
+        _ => unreachable!("Invalid, rule"),
+    }
+}
+
+/// the parameter types and the return type of the function bound to a hook of `MiddleWare` or
+/// `PipeLine`, `E` standing for the entities and `_` for any type
+pub(crate) fn get_hook_signature(
+    version: &str,
+    hook: &str,
+) -> Option<(&'static [&'static str], &'static str)> {
+    // the supported versions take the same arguments
+    let signature: (&'static [&'static str], &'static str) = match (version, hook) {
+        (_, "handle_task") => (&["&mut Vec<Task>", "&mut App<E>"], "()"),
+        (_, "handle_affix") => (&["&mut Vec<Affix>", "&mut App<E>"], "()"),
+        (_, "handle_entity") => (&["&mut Vec<E>", "&mut App<E>"], "()"),
+        (_, "handle_req") => (&["&mut Vec<Request>", "&mut App<E>"], "()"),
+        (_, "handle_res") => (&["&mut Vec<Response>", "&mut App<E>"], "()"),
+        (_, "handle_err") | (_, "handle_yerr") => (
+            &["&mut Vec<Result<Response, MetaResponse>>", "&mut App<E>"],
+            "()",
+        ),
+        (_, "initializer") => (&["&mut App<E>"], "Option<_>"),
+        (_, "disposer") => (&["&mut App<E>"], "()"),
+        (_, "process_entity") => (&["Vec<E>", "&mut App<E>"], "()"),
+        (_, "process_yerr") => (
+            &["Vec<Result<Response, MetaResponse>>", "&mut App<E>"],
+            "()",
+        ),
+        _ => return None,
+    };
+    Some(signature)
+}
+
+/// the file stem of the generated main, `path = "src/bin/<+stem+>.rs"` of `[[bin]]`
 pub fn get_package_name() -> String {
     let pat1 = regex::Regex::new(r"^\s*path\s*=.*?src/bin/(?P<pkg_name>[\w|-]+)\.rs").unwrap();
     let file = std::fs::File::open("./Cargo.toml").expect("Cargo.toml file cannot be found!");
@@ -37,14 +134,14 @@ pub fn get_package_name() -> String {
                 .name("pkg_name")
                 .unwrap()
                 .as_str()
-                .replace("-", "_");
-            return name.into();
+                .to_string();
+            return name;
         }
     }
     panic!("the target file cannot be found, put `path = 'src/bin/<+target+>.rs'` in your Cargo.toml file!");
 }
 
-pub(crate) fn get_file_path<'a>(index: &'a str, name: String) -> String {
+pub(crate) fn get_file_path(index: &str, name: String) -> String {
     match index {
         "readme" => name + "/README.md",
         "config" => name + "/dyer.cfg",
@@ -55,6 +152,7 @@ pub(crate) fn get_file_path<'a>(index: &'a str, name: String) -> String {
         "actor" => name + "/src/actor.rs",
         "middleware" => name + "/src/middleware.rs",
         "pipeline" => name + "/src/pipeline.rs",
+        "gitignore" => name + "/.gitignore",
         _ => {
             println!("Invalid name: {}", index);
             panic!()
@@ -185,7 +283,7 @@ impl Actor<Entities, Aff> for MyActor {
     // create an instance 
     async fn new() -> Self {
         MyActor{
-            start_uri: "https://example.com/some/path/to/site".into()
+            start_uri: "<+start_uri+>".into()
         }
     }
 
@@ -198,11 +296,11 @@ impl Actor<Entities, Aff> for MyActor {
      */
     async fn entry_task(&mut self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
         let task = Task::get( &self.start_uri ) 
-            .parser(parse_func)
+            .parser(<+entry_parser+>)
             // here the marker `MyActor` is the same as 
             // the type implemented trait `Acotr` 
             // change it if you like as long as it is unique
-            .body(Body::empty(), "MyActor")
+            .body(Body::empty(), <+actor_marker+>)
             .unwrap();
         Ok(vec![task])
     }
@@ -223,23 +321,23 @@ impl Actor<Entities, Aff> for MyActor {
             r#"[package]
 name = "<+name+>"
 version = "0.1.0"
-authors = ["your name"]
+authors = ["<+author+>"]
 edition = "2018"
 
 # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
 
 [lib]
-name = "<+name+>"
+name = "<+crate_name+>"
 path = "src/actor.rs"
 
 [[bin]]
 name = "<+name+>"
-path = "src/bin/<+name+>.rs"
+path = "src/bin/<+crate_name+>.rs"
 
 [dependencies]
-dyer = { version = "3.3", features = [] }
+dyer = { version = "<+dyer_version+>", features = [<+dyer_features+>] }
 serde = { version = "1.0", features = ["derive"] }
-tokio = { version = "1.20", features = ["rt-multi-thread"]}
+tokio = <+tokio_dependency+>
 simple_logger = "1.11" "#
         }
         "config" => {
@@ -263,42 +361,154 @@ rate.load: 99.0,
 rate.rate_low: 0.333,
 rate.err: 0,
 rate.interval: 30.0,
+"#
+        }
+        "no_affix" => {
+            r#"
+// the actor generates no affix, `entry_affix` returns `None` and it is never invoked
+pub struct NoAffix;
+
+#[dyer::async_trait]
+impl Affixor for NoAffix {
+    async fn init(&mut self) {}
+    async fn invoke(&mut self) -> Option<Request> {
+        None
+    }
+    async fn after_invoke(&mut self) {}
+    async fn before_parse(&mut self, _: Option<&mut Result<Response, MetaResponse>>) {}
+    async fn parse(&mut self, _: Option<Result<Response, MetaResponse>>) -> Option<Affix> {
+        None
+    }
+    async fn after_parse(&mut self) {}
+    async fn close(&mut self) {}
+}
+"#
+        }
+        "gitignore" => {
+            r#"/target
+/data
+/.dyer-fingerprint
+# generated by `dyer test-parser` from the fixtures
+/tests/dyer_parsers.rs
+# the main generated by `dyer run` is kept for its user regions and `dyer gen --check`,
+# uncomment the line to ignore it
+#/src/bin/<+crate_name+>.rs
 "#
         }
         _ => "",
     }
 }
 
-pub(crate) fn run_command(cmd: &str, options: Vec<&str>) {
-    let stdout = std::process::Command::new(cmd)
+/// the actor without the affix module, its `Affixor` is the inert `NoAffix`
+pub(crate) fn remove_affix(actor: &str) -> String {
+    let lines = actor
+        .lines()
+        .filter(|line| !["pub mod affix;", "use affix::*;"].contains(&line.trim()))
+        .collect::<Vec<&str>>();
+    let buf = lines
+        .join("\n")
+        .replace("Actor<Entities, Aff>", "Actor<Entities, NoAffix>")
+        .replace("Option<Aff>", "Option<NoAffix>");
+    format!("{}\n{}", buf.trim_end(), get_file_intro("no_affix"))
+}
+
+/// pid of the running child of dyer-cli, 0 if there is none
+#[cfg(unix)]
+static CHILD: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);
+
+/// whether dyer-cli is interrupted by SIGINT or SIGTERM
+static INTERRUPTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" {
+    fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    fn kill(pid: i32, sig: i32) -> i32;
+}
+
+#[cfg(unix)]
+const SIGINT: i32 = 2;
+#[cfg(unix)]
+const SIGTERM: i32 = 15;
+
+/// pass SIGTERM on to the child, so that it shuts down gracefully before dyer-cli exits,
+/// SIGINT from the terminal is delivered to the child by the foreground process group already
+#[cfg(unix)]
+extern "C" fn forward_signal(sig: i32) {
+    INTERRUPTED.store(true, std::sync::atomic::Ordering::SeqCst);
+    let child = CHILD.load(std::sync::atomic::Ordering::SeqCst);
+    if sig == SIGTERM && child > 0 {
+        unsafe {
+            kill(child, sig);
+        }
+    }
+}
+
+/// keep dyer-cli alive till its child exits, instead of being killed by SIGINT or SIGTERM first
+pub(crate) fn forward_signals() {
+    #[cfg(unix)]
+    unsafe {
+        signal(SIGINT, forward_signal);
+        signal(SIGTERM, forward_signal);
+    }
+}
+
+/// the child the signals are forwarded to, `None` once it exits
+pub(crate) fn set_child(child: Option<&std::process::Child>) {
+    #[cfg(unix)]
+    CHILD.store(
+        child.map(|c| c.id() as i32).unwrap_or(0),
+        std::sync::atomic::Ordering::SeqCst,
+    );
+    #[cfg(not(unix))]
+    let _ = child;
+}
+
+pub(crate) fn is_interrupted() -> bool {
+    INTERRUPTED.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+/// stop the child gracefully with SIGTERM(killed where there is no signal) and wait for it
+pub(crate) fn terminate(child: &mut std::process::Child) {
+    #[cfg(unix)]
+    unsafe {
+        kill(child.id() as i32, SIGTERM);
+    }
+    #[cfg(not(unix))]
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// run the command with its output filtered, whether it succeeds
+pub(crate) fn run_command(cmd: &str, options: Vec<&str>) -> bool {
+    forward_signals();
+    let mut child = std::process::Command::new(cmd)
         .args(options)
         .stdout(std::process::Stdio::piped())
         .spawn()
-        .unwrap()
+        .unwrap_or_else(|e| exit_with(&format!("failed to run `{}`: {}", cmd, e)));
+    set_child(Some(&child));
+    let stdout = child
         .stdout
-        .ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Could not capture standard output.",
-            )
-        })
+        .take()
+        .ok_or_else(|| std::io::Error::other("Could not capture standard output."))
         .unwrap();
 
     let reader = BufReader::new(stdout);
 
     reader
         .lines()
-        .filter_map(|line| line.ok())
+        .map_while(Result::ok)
         .filter(|line| line.find("src\\main.rs").is_none())
         .filter(|line| line.find("src/main.rs").is_none())
         .for_each(|line| println!("{}", line));
+    let status = child.wait();
+    set_child(None);
+    status.map(|s| s.success()).unwrap_or(false)
 }
 
 pub(crate) fn change_log_level(level: &str) {
-    let mut file = std::fs::OpenOptions::new()
-        .read(true)
-        .open("src/bin/main.rs")
-        .unwrap();
+    let main = format!("src/bin/{}.rs", get_package_name());
+    let mut file = std::fs::OpenOptions::new().read(true).open(&main).unwrap();
     let mut buf = String::new();
     file.read_to_string(&mut buf).unwrap();
     drop(file);
@@ -313,18 +523,379 @@ pub(crate) fn change_log_level(level: &str) {
     let mut file = std::fs::OpenOptions::new()
         .truncate(true)
         .write(true)
-        .open("src/bin/main.rs")
+        .open(&main)
         .unwrap();
     file.write_all(buf.as_bytes()).unwrap();
 }
 
+/// 64-bit FNV-1a, unlike `DefaultHasher` it is the same across versions of Rust
+pub(crate) fn stable_hash(buf: &[u8]) -> u64 {
+    buf.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// the candidate closest to `word` by edit distance, `None` if none is close enough to be a typo
+pub(crate) fn get_closest<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let distance = |a: &str, b: &str| {
+        let b = b.chars().collect::<Vec<char>>();
+        let mut row = (0..=b.len()).collect::<Vec<usize>>();
+        for (i, ca) in a.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, cb) in b.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = (diagonal + usize::from(ca != *cb))
+                    .min(row[j] + 1)
+                    .min(above + 1);
+                diagonal = above;
+            }
+        }
+        row[b.len()]
+    };
+    candidates
+        .iter()
+        .map(|c| (distance(word, c), *c))
+        .filter(|(d, _)| *d <= (word.chars().count() / 3).max(2))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
 pub(crate) fn to_camelcase(s: &str) -> String {
     let mut r = String::with_capacity(s.len());
-    let mut ch = s[..].chars();
+    let mut ch = s.chars();
     let e = ch.next().unwrap().to_uppercase();
     r.push(e.to_string().chars().next().unwrap());
-    while let Some(t) = ch.next() {
+    for t in ch {
         r.push(t);
     }
     r
 }
+
+/// collect the values of an option given as `--key=value` or `--key value`
+pub(crate) fn get_option_values(options: &[String], key: &str) -> Vec<String> {
+    let prefix = format!("{}=", key);
+    options
+        .iter()
+        .filter_map(|op| op.strip_prefix(&prefix))
+        .map(|val| val.to_string())
+        .collect()
+}
+
+/// the author of the project, taken from git config as cargo does
+pub(crate) fn get_author() -> String {
+    let git_config = |key: &str| {
+        std::process::Command::new("git")
+            .args(["config", "--get", key])
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+            .filter(|val| !val.is_empty())
+    };
+    let name = git_config("user.name")
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .unwrap_or_else(|| "your name".into());
+    match git_config("user.email") {
+        Some(email) => format!("{} <{}>", name, email),
+        None => name,
+    }
+}
+
+/// print the message to stderr and quit with non-zero code
+pub(crate) fn exit_with(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
+}
+
+/// merge the manifest `template` into the `existing` one, add the dependencies missing in
+/// `[dependencies]` and the `sections`(`lib` or `bin`) if not specified, return the merged
+/// manifest and what are added
+pub(crate) fn merge_manifest(
+    existing: &str,
+    template: &str,
+    sections: &[&str],
+) -> (String, Vec<String>) {
+    let header = regex::Regex::new(r"^\s*\[\[?(?P<section>[\w\.-]+)\]?\]").unwrap();
+    let key = regex::Regex::new(r"^\s*(?P<key>[\w-]+)\s*=").unwrap();
+    let section_of = |buf: &str, name: &str| -> Option<Vec<String>> {
+        let mut lines = None;
+        let mut current = String::new();
+        for line in buf.lines() {
+            if let Some(cap) = header.captures(line) {
+                current = cap["section"].to_string();
+                if current == name {
+                    lines.get_or_insert_with(Vec::new);
+                }
+                continue;
+            }
+            if current == name {
+                if let Some(lines) = lines.as_mut() {
+                    lines.push(line.to_string());
+                }
+            }
+        }
+        lines
+    };
+    let mut lines = existing.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+    let mut added = Vec::new();
+
+    // dependencies are compared by name regardless of `-` and `_`
+    let normalize = |line: &str| key.captures(line).map(|c| c["key"].replace('-', "_"));
+    let existing_deps = section_of(existing, "dependencies")
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|l| normalize(l))
+        .collect::<Vec<_>>();
+    let missing = section_of(template, "dependencies")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|l| matches!(normalize(l), Some(k) if !existing_deps.contains(&k)))
+        .map(|l| l.trim().to_string())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        let start = lines.iter().position(|l| {
+            header.captures(l).map(|c| &c["section"] == "dependencies") == Some(true)
+        });
+        match start {
+            Some(start) => {
+                // insert after the last entry of the section
+                let mut end = start + 1;
+                for (i, line) in lines.iter().enumerate().skip(start + 1) {
+                    if header.is_match(line) {
+                        break;
+                    }
+                    if !line.trim().is_empty() {
+                        end = i + 1;
+                    }
+                }
+                for (i, dep) in missing.iter().enumerate() {
+                    lines.insert(end + i, dep.clone());
+                }
+            }
+            None => {
+                lines.push(String::new());
+                lines.push("[dependencies]".into());
+                lines.extend(missing.iter().cloned());
+            }
+        }
+        added.extend(missing.iter().map(|dep| format!("dependency: {}", dep)));
+    }
+    // the binary of dyer is the one at `src/bin/`, other binaries do not count
+    let bin_path = regex::Regex::new(r"^\s*path\s*=.*?src/bin/[\w|-]+\.rs").unwrap();
+    for name in sections.iter() {
+        let exists = match *name {
+            "bin" => existing.lines().any(|l| bin_path.is_match(l)),
+            _ => section_of(existing, name).is_some(),
+        };
+        if exists {
+            continue;
+        }
+        if let Some(body) = section_of(template, name) {
+            let title = if name == &"bin" { "[[bin]]" } else { "[lib]" };
+            lines.push(String::new());
+            lines.push(title.to_string());
+            lines.extend(body.iter().filter(|l| !l.trim().is_empty()).cloned());
+            added.push(format!("section: {}", title));
+        }
+    }
+    let mut merged = lines.join("\n");
+    merged.push('\n');
+    (merged, added)
+}
+
+/// the string value of `key` in `[section]` of manifest, eg. `name` of `package`
+pub(crate) fn get_manifest_value(manifest: &str, section: &str, key: &str) -> Option<String> {
+    let header = regex::Regex::new(r"^\s*\[(?P<section>[^\]]*)\]").unwrap();
+    let pat = format!(
+        r#"^\s*{}\s*=\s*["'](?P<value>[^"']+)["']"#,
+        regex::escape(key)
+    );
+    let value = regex::Regex::new(&pat).unwrap();
+    let mut in_section = false;
+    for line in manifest.lines() {
+        if let Some(cap) = header.captures(line) {
+            in_section = cap["section"].trim() == section;
+        } else if in_section {
+            if let Some(cap) = value.captures(line) {
+                return Some(cap["value"].to_string());
+            }
+        }
+    }
+    None
+}
+
+/// the keys and values of `[section]` of manifest, quotes of strings removed
+pub(crate) fn get_manifest_table(manifest: &str, section: &str) -> Vec<(String, String)> {
+    let header = regex::Regex::new(r"^\s*\[(?P<section>[^\]]*)\]").unwrap();
+    let pair = regex::Regex::new(r"^\s*(?P<key>[\w-]+)\s*=\s*(?P<value>.*?)\s*$").unwrap();
+    let mut in_section = false;
+    let mut pairs = Vec::new();
+    for line in manifest.lines() {
+        if let Some(cap) = header.captures(line) {
+            in_section = cap["section"].trim() == section;
+        } else if in_section {
+            if let Some(cap) = pair.captures(line) {
+                let value = cap["value"].trim_matches(|c| c == '"' || c == '\'');
+                pairs.push((cap["key"].to_string(), value.to_string()));
+            }
+        }
+    }
+    pairs
+}
+
+/// the headers of `[package.metadata.dyer.headers]` set on the requests, the names in lowercase, or
+/// the problem of the first invalid one
+pub(crate) fn get_configured_headers(manifest: &str) -> Result<Vec<(String, String)>, String> {
+    let name = regex::Regex::new(r"^[!#$%&'*+.^_`|~0-9a-z-]+$").unwrap();
+    let mut headers = Vec::new();
+    for (key, value) in get_manifest_table(manifest, "package.metadata.dyer.headers") {
+        let key = key.to_lowercase();
+        if !name.is_match(&key) {
+            return Err(format!("`{}` is not a valid name of header", key));
+        }
+        if value.chars().any(|c| c.is_control()) {
+            return Err(format!(
+                "the value of header `{}` has control characters",
+                key
+            ));
+        }
+        headers.push((key, value));
+    }
+    Ok(headers)
+}
+
+/// the keywords of Rust, strict and reserved, which cannot be identifiers
+pub(crate) const KEYWORDS: [&str; 50] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+    "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// names that cannot be used as package because they collide with the standard or dependencies
+const RESERVED_NAMES: [&str; 10] = [
+    "test",
+    "std",
+    "core",
+    "alloc",
+    "proc_macro",
+    "dyer",
+    "serde",
+    "tokio",
+    "simple_logger",
+    "log",
+];
+
+/// check the name of package and derive the crate identifier from it
+pub(crate) fn validate_name(name: &str) -> Result<String, String> {
+    if name.is_empty() {
+        return Err("the name of project cannot be empty".into());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        let suggestion = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>()
+            .to_lowercase();
+        return Err(format!(
+            "invalid character `{}` in project name `{}`, only letters, digits, `-` and `_` are allowed, such as `{}`",
+            c, name, suggestion
+        ));
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(format!(
+            "the name of project `{}` cannot start with a digit",
+            name
+        ));
+    }
+    if name.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(format!(
+            "the name of project `{}` should be lowercase, such as `{}`",
+            name,
+            name.to_lowercase()
+        ));
+    }
+    let ident = name.replace('-', "_");
+    if [name, ident.as_str()]
+        .iter()
+        .any(|n| KEYWORDS.contains(n) || RESERVED_NAMES.contains(n))
+    {
+        return Err(format!(
+            "`{}` is a Rust keyword or a reserved crate name and cannot be the name of project, use `--name` to choose another one",
+            name
+        ));
+    }
+    Ok(ident)
+}
+
+/// quote and escape `s` as a json string
+pub(crate) fn json_str(s: &str) -> String {
+    let mut buf = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_name_derives_the_crate_identifier() {
+        assert_eq!(validate_name("my-crawler"), Ok("my_crawler".to_string()));
+        assert_eq!(validate_name("crawler_2"), Ok("crawler_2".to_string()));
+    }
+
+    #[test]
+    fn validate_name_rejects_invalid_names() {
+        assert!(validate_name("").is_err());
+        assert!(validate_name("my crawler")
+            .unwrap_err()
+            .contains("`my_crawler`"));
+        assert!(validate_name("2crawler").is_err());
+        assert!(validate_name("Crawler").unwrap_err().contains("`crawler`"));
+    }
+
+    #[test]
+    fn validate_name_rejects_keywords_and_dependencies() {
+        for name in [
+            "fn",
+            "type",
+            "self",
+            "std",
+            "dyer",
+            "tokio",
+            "log",
+            "simple-logger",
+        ] {
+            assert!(validate_name(name).is_err(), "`{}` is accepted", name);
+        }
+    }
+
+    #[test]
+    fn get_closest_suggests_the_candidate_of_a_typo() {
+        let hooks = ["handle_task", "handle_req", "handle_res", "initializer"];
+        assert_eq!(get_closest("handle_tsk", &hooks), Some("handle_task"));
+        assert_eq!(get_closest("initialiser", &hooks), Some("initializer"));
+        assert_eq!(get_closest("handle_rez", &hooks), Some("handle_req"));
+        assert_eq!(get_closest("on_start", &hooks), None);
+        assert_eq!(get_closest("", &hooks), None);
+        assert_eq!(get_closest("handle_task", &[]), None);
+    }
+}